@@ -0,0 +1,121 @@
+//! Time spent finding targets for thousands of minions, with the spatial grid and by checking
+//! every pair like minions used to. Both find the same targets, the unit tests of
+//! `game::spatial` check it.
+//!
+//! `cargo run --release --example minions_bench [MINIONS]` (or `make bench-minions`)
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_turborand::prelude::*;
+use game::{
+    spatial::SpatialGrid,
+    teams::{TeamId, Teams},
+};
+
+const DEFAULT_MINIONS: usize = 2000;
+const TEAMS: usize = 4;
+const TICKS: u32 = 60;
+// every other tick looks as far as minions see, the others without limit
+const VISION_RADIUS: f32 = 250.;
+// the default arena
+const WORLD_SIZE: f32 = 2000.;
+// a tick at 60 fps
+const BUDGET: Duration = Duration::from_micros(16_667);
+
+// how many units have a hostile unit within `radius`, checking every pair
+fn brute_force(units: &[(Entity, Vec2, TeamId)], teams: &Teams, radius: f32) -> usize {
+    units
+        .iter()
+        .filter_map(|(_, position, team)| {
+            units
+                .iter()
+                .filter(|(_, _, other)| teams.is_hostile(*team, *other))
+                .map(|(_, other, _)| position.distance_squared(*other))
+                .filter(|distance_squared| *distance_squared <= radius * radius)
+                .min_by(f32::total_cmp)
+        })
+        .count()
+}
+
+// how many units have a hostile unit within `radius`, with the grid rebuilt as on every tick
+fn grid(
+    grid: &mut SpatialGrid,
+    units: &[(Entity, Vec2, TeamId)],
+    teams: &Teams,
+    radius: f32,
+) -> usize {
+    grid.clear();
+    for (entity, position, team) in units {
+        grid.insert(*entity, *position, *team);
+    }
+
+    units
+        .iter()
+        .filter_map(|(_, position, team)| grid.nearest_hostile(teams, *position, *team, radius))
+        .count()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let per_tick = elapsed / TICKS;
+    let verdict = if per_tick <= BUDGET {
+        "fits"
+    } else {
+        "does not fit"
+    };
+    println!("{name:>12}: {per_tick:?} per tick, {verdict} in a 60 fps frame");
+}
+
+fn main() {
+    let minions = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("MINIONS must be a number"))
+        .unwrap_or(DEFAULT_MINIONS);
+
+    let mut teams = Teams::new();
+    for index in 0..TEAMS {
+        teams.add(index.to_string(), Color::WHITE);
+    }
+
+    let mut rng = RngComponent::with_seed(0x2545_f491);
+    let mut units: Vec<(Entity, Vec2, TeamId)> = (0..minions)
+        .map(|index| {
+            let position = Vec2::new(rng.f32_normalized(), rng.f32_normalized());
+            (
+                Entity::from_raw(index as u32),
+                position * WORLD_SIZE / 2.,
+                TeamId((index % TEAMS) as u8),
+            )
+        })
+        .collect();
+
+    println!("{minions} minions, {TEAMS} teams, {TICKS} ticks");
+
+    let mut spatial_grid = SpatialGrid::default();
+    let (mut grid_elapsed, mut brute_force_elapsed) = (Duration::ZERO, Duration::ZERO);
+    for tick in 0..TICKS {
+        let radius = if tick % 2 == 0 {
+            f32::INFINITY
+        } else {
+            VISION_RADIUS
+        };
+
+        let start = Instant::now();
+        black_box(grid(&mut spatial_grid, black_box(&units), &teams, radius));
+        grid_elapsed += start.elapsed();
+
+        let start = Instant::now();
+        black_box(brute_force(black_box(&units), &teams, radius));
+        brute_force_elapsed += start.elapsed();
+
+        // minions move a bit every tick
+        for (_, position, _) in &mut units {
+            *position += Vec2::new(rng.f32_normalized(), rng.f32_normalized()) * 3.;
+        }
+    }
+    report("grid", grid_elapsed);
+    report("every pair", brute_force_elapsed);
+}
@@ -3,27 +3,35 @@ use bevy_cameraman::Cameraman;
 
 const DEBUG: bool = false;
 const MUSIC_AUDIO_ID: &str = "sounds/music.ogg";
+const EXPLOSION_AUDIO_ID: &str = "sounds/explosion.ogg";
+
+/// Sound played by explosions, only available when the `AudioPlugin` is added.
+#[derive(Resource)]
+pub struct AudioExplosion(pub Handle<AudioSource>);
 
 pub struct AudioPlugin;
 
 impl Plugin for AudioPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PostStartup, setup);
+        app.add_systems(Startup, setup_sounds)
+            .add_systems(Update, add_listeners);
     }
 }
 
-fn setup(
-    mut commands: Commands,
-    server: Res<AssetServer>,
-    query_camera: Query<Entity, With<Cameraman>>,
-) {
-    let gap = 200.;
-    let listener = SpatialListener::new(gap);
+fn setup_sounds(mut commands: Commands, server: Res<AssetServer>) {
+    let handle = server.load(EXPLOSION_AUDIO_ID);
+    commands.insert_resource(AudioExplosion(handle));
 
     commands.spawn(AudioBundle {
         source: server.load(MUSIC_AUDIO_ID),
         settings: PlaybackSettings::LOOP.with_spatial(false),
     });
+}
+
+// cameras are spawned with every match
+fn add_listeners(mut commands: Commands, query_camera: Query<Entity, Added<Cameraman>>) {
+    let gap = 200.;
+    let listener = SpatialListener::new(gap);
 
     for entity in &query_camera {
         let mut cmd = commands.entity(entity);
@@ -0,0 +1,237 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    prelude::*,
+    reflect::TypePath,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    health::{Regeneration, Resistances},
+    minions::MinionKind,
+    state::GameState,
+};
+
+pub const BALANCE_ASSET_PATH: &str = "balance.ron";
+
+/// Every gameplay number designers can tune.
+///
+/// Loaded from `assets/balance.ron` into the `Balance` resource, and reloaded when the file changes
+/// (with the `bevy/file_watcher` feature). Values are read when entities are spawned,
+/// so a reload applies to new minions, racks, etc.
+#[derive(Asset, Resource, TypePath, Deserialize, Clone, Debug)]
+pub struct Balance {
+    pub minions: MinionKinds,
+    pub rack: RackBalance,
+    pub castle: CastleBalance,
+    pub player: PlayerBalance,
+}
+
+/// Stats of every `MinionKind`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct MinionKinds {
+    pub kamikaze: MinionBalance,
+    pub melee: MinionBalance,
+    pub ranged: MinionBalance,
+    pub tank: MinionBalance,
+    pub healer: MinionBalance,
+}
+
+impl MinionKinds {
+    pub fn get(&self, kind: MinionKind) -> &MinionBalance {
+        match kind {
+            MinionKind::Kamikaze => &self.kamikaze,
+            MinionKind::Melee => &self.melee,
+            MinionKind::Ranged => &self.ranged,
+            MinionKind::Tank => &self.tank,
+            MinionKind::Healer => &self.healer,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct MinionBalance {
+    /// size of the minion
+    pub radius: f32,
+    /// pixels per second
+    pub speed: f32,
+    /// hostile units closer than this are chased, others are ignored while marching
+    pub vision_radius: f32,
+    /// hostile units closer than this are attacked, allies closer than this are healed
+    pub attack_radius: f32,
+    /// share of the health under which minions flee threats, 0 to never flee
+    pub retreat_health: f32,
+    pub health: f32,
+    /// health lost every second
+    pub decay_per_sec: f32,
+    /// dealt by the explosion of kamikazes, the hits of melees and tanks, the projectiles of ranged
+    pub damage: f32,
+    /// seconds between two hits, shots or heals
+    #[serde(default)]
+    pub cooldown_secs: f32,
+    /// pixels per second of the projectiles of ranged
+    #[serde(default)]
+    pub projectile_speed: f32,
+    /// health given back to every ally around by healers
+    #[serde(default)]
+    pub heal: f32,
+    pub lifetime_secs: f32,
+    pub rewards_gold: f32,
+    #[serde(default)]
+    pub resistances: Resistances,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct RackBalance {
+    pub health: f32,
+    /// gold spent by a player to build a rack
+    pub gold_value: f32,
+    pub rewards_gold: f32,
+    #[serde(default)]
+    pub resistances: Resistances,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CastleBalance {
+    pub health: f32,
+    pub rewards_gold: f32,
+    #[serde(default)]
+    pub resistances: Resistances,
+    #[serde(default)]
+    pub regeneration: Regeneration,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PlayerBalance {
+    /// pixels per second
+    pub speed: f32,
+    pub health: f32,
+    pub gold: f32,
+    pub sword_damage: f32,
+    pub sword_cooldown_secs: f32,
+    /// seconds dead before coming back at the team castle
+    pub respawn_secs: f32,
+    #[serde(default)]
+    pub resistances: Resistances,
+    #[serde(default)]
+    pub regeneration: Regeneration,
+}
+
+// the values shipped in `assets/balance.ron`, used until it is loaded or when it can't be
+impl Default for Balance {
+    fn default() -> Self {
+        ron::de::from_str(include_str!("../assets/balance.ron"))
+            .expect("assets/balance.ron should parse")
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BalanceLoaderError {
+    #[error("could not read balance: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse balance: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+pub struct BalanceLoader;
+
+impl AssetLoader for BalanceLoader {
+    type Asset = Balance;
+    type Settings = ();
+    type Error = BalanceLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<Balance>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+#[derive(Resource)]
+pub struct BalanceHandle(Handle<Balance>);
+
+pub struct BalancePlugin;
+
+impl Plugin for BalancePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Balance>()
+            .init_asset::<Balance>()
+            .init_asset_loader::<BalanceLoader>()
+            .add_systems(Startup, load)
+            .add_systems(OnExit(GameState::Loading), apply)
+            .add_systems(PreUpdate, update);
+    }
+}
+
+/// Run condition, true once `balance.ron` is loaded or failed to (defaults are kept).
+pub fn loaded(handle: Res<BalanceHandle>, server: Res<AssetServer>) -> bool {
+    matches!(
+        server.get_load_state(handle.0.id()),
+        Some(LoadState::Loaded | LoadState::Failed)
+    )
+}
+
+fn load(mut commands: Commands, server: Res<AssetServer>) {
+    commands.insert_resource(BalanceHandle(server.load(BALANCE_ASSET_PATH)));
+}
+
+// the first match uses the loaded values, even if the loaded event was not read yet
+fn apply(handle: Res<BalanceHandle>, assets: Res<Assets<Balance>>, mut balance: ResMut<Balance>) {
+    match assets.get(&handle.0) {
+        Some(loaded) => *balance = loaded.clone(),
+        None => warn!(
+            "[balance] could not load {}, using defaults",
+            BALANCE_ASSET_PATH
+        ),
+    }
+}
+
+// copies the asset into the resource once loaded, and every time the file changes
+fn update(
+    handle: Res<BalanceHandle>,
+    assets: Res<Assets<Balance>>,
+    mut events: EventReader<AssetEvent<Balance>>,
+    mut balance: ResMut<Balance>,
+) {
+    for event in events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == handle.0.id() =>
+            {
+                if let Some(loaded) = assets.get(*id) {
+                    info!("[balance] loaded");
+                    *balance = loaded.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_the_shipped_balance() {
+        let balance = Balance::default();
+        assert_eq!(balance.player.respawn_secs, 5.);
+        assert_eq!(
+            balance.minions.get(MinionKind::Ranged).projectile_speed,
+            400.
+        );
+    }
+}
@@ -4,32 +4,48 @@ use bevy::{
     time::{Timer, TimerMode},
 };
 use bevy_rapier2d::prelude::*;
+use bevy_turborand::prelude::*;
 
 use crate::{
+    balance::Balance,
     common::Rewards,
-    health::Health,
+    health::{DespawnOnDeath, Health, Regeneration, Resistances},
+    minions::MinionKind,
+    navigation::NavObstacle,
     racks::Rack,
-    teams::{Team, Teams},
+    state::MatchEntity,
+    teams::{Team, TeamId},
 };
 
 // TODO: Castle is a rack two...
 // TODO: So maybe having a common "minion spawner" rather than "rack" is better?
 
+#[derive(Component)]
+pub struct Castle;
+
 #[derive(Bundle)]
 pub struct CastleBundle {
     pub sprite_bundle: SpriteBundle,
-    pub team: Team,
+    pub castle: Castle,
+    pub match_entity: MatchEntity,
+    pub team: TeamId,
     pub rack: Rack,
     pub health: Health,
+    pub resistances: Resistances,
+    pub regeneration: Regeneration,
+    pub despawn_on_death: DespawnOnDeath,
     pub rewards: Rewards,
+    pub nav_obstacle: NavObstacle,
     pub rigid_body: RigidBody,
     pub collider: Collider,
+    pub velocity: Velocity,
     pub events: ActiveEvents,
     pub mass: ColliderMassProperties,
+    pub rng: RngComponent,
 }
 
 impl CastleBundle {
-    pub fn new(team: Team, transform: Transform) -> Self {
+    pub fn new(team: &Team, transform: Transform, rng: RngComponent, balance: &Balance) -> Self {
         let size = Vec2::new(80.0, 80.0);
         CastleBundle {
             sprite_bundle: SpriteBundle {
@@ -41,61 +57,39 @@ impl CastleBundle {
                 transform,
                 ..default()
             },
-            team,
+            castle: Castle,
+            match_entity: MatchEntity,
+            team: team.id,
             rack: Rack {
                 minion_spawning: false,
                 minion_spawned_count: 0,
                 minion_spawn_count: 5,
                 minion_spawn_timer: Timer::from_seconds(3., TimerMode::Repeating),
                 minion_spawn_timer_q: Timer::from_seconds(0.2, TimerMode::Repeating),
+                // castles defend themselves
+                minion_kind: MinionKind::Melee,
             },
-            health: Health::new(1000.)
+            health: Health::new(balance.castle.health)
                 .with_health_bar_position(Vec3::new(0.0, 50.0, 0.0))
                 .with_health_bar_size(Vec2::new(size.x, 5.)),
-            rewards: Rewards { gold: 500. },
+            resistances: balance.castle.resistances,
+            regeneration: balance.castle.regeneration,
+            despawn_on_death: DespawnOnDeath,
+            rewards: Rewards {
+                gold: balance.castle.rewards_gold,
+            },
+            nav_obstacle: NavObstacle,
             rigid_body: RigidBody::Dynamic,
             collider: Collider::cuboid((size.x / 2.) * 0.98, (size.y / 2.) * 0.98),
+            velocity: Velocity::zero(),
             events: ActiveEvents::COLLISION_EVENTS,
             mass: ColliderMassProperties::Mass(0.),
+            rng,
         }
     }
-}
 
-pub struct CastlesPlugin;
-
-impl Plugin for CastlesPlugin {
-    fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup)
-            .add_systems(PostUpdate, destroy);
-    }
-}
-
-fn setup(mut commands: Commands, teams: Res<Teams>) {
-    commands.spawn(CastleBundle::new(
-        teams.get_expect("a".into()),
-        Transform::from_xyz(-200.0, -300.0, 0.),
-    ));
-    commands.spawn(CastleBundle::new(
-        teams.get_expect("b".into()),
-        Transform::from_xyz(300.0, 300.0, 0.),
-    ));
-    commands.spawn(CastleBundle::new(
-        teams.get_expect("c".into()),
-        Transform::from_xyz(200.0, -200.0, 0.),
-    ));
-}
-
-// TODO: maybe this system can be retrieve from health bar crate (give the type and insert it in the filter?)
-fn destroy(mut commands: Commands, mut query: Query<(&Health, Entity), With<Rack>>) {
-    let mut kill = |entity| {
-        trace!("Unspawning Minion: {:?}", entity);
-        commands.entity(entity).despawn_recursive();
-    };
-
-    for (health, entity) in &mut query {
-        // just not enough health
-        if health.is_dead() {
-            kill(entity);
-        }
+    pub fn with_minion_kind(mut self, kind: MinionKind) -> Self {
+        self.rack.minion_kind = kind;
+        self
     }
 }
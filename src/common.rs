@@ -1,8 +1,40 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub const GAME_MAX_WIDTH: f32 = 2000.;
 pub const GAME_MAX_HEIGHT: f32 = 2000.;
 
+/// Size of the world, centered on the origin, minions going out are destroyed.
+#[derive(Resource)]
+pub struct WorldBounds {
+    pub size: Vec2,
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        Self {
+            size: Vec2::new(GAME_MAX_WIDTH, GAME_MAX_HEIGHT),
+        }
+    }
+}
+
+/// Gameplay systems run in `FixedUpdate`, after the physics step, in this order.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    /// Turns physics contacts into `CollisionEvent`s, indexes units in the `SpatialGrid`
+    Collisions,
+    /// Movements, attacks, spawns, etc
+    Logic,
+    /// Applies every `DamageEvent` sent until then
+    Damage,
+    /// Despawns what is dead or expired
+    Cleanup,
+}
+
+/// Number of fixed updates since the match started.
+#[derive(Resource, Default)]
+pub struct Tick(pub u64);
+
 #[derive(Component)]
 pub struct Target {
     pub position: Vec3,
@@ -11,7 +43,13 @@ pub struct Target {
 #[derive(Component)]
 pub struct Name(pub String);
 
-#[derive(Component)]
+/// Despawns the entity once the timer finishes, entities having health die instead.
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct TimeDestroyable {
+    pub timer: Timer,
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Rewards {
     pub gold: f32,
 }
@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use bevy_turborand::prelude::*;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{maps::MapSet, state::GameState};
+
+pub const DEFAULT_SEED: &str = "13U2x";
+pub const DEFAULT_LOG_FILTER: &str = concat!(
+    "wgpu=error,",
+    "bevy_render=warn,bevy_app=warn,bevy_ecs=warn,",
+    "naga=warn,",
+    "gilrs=warn,",
+    "game::health=info,game::racks=info",
+);
+
+pub const USAGE: &str = "Usage: game [OPTIONS]
+
+Options:
+  --seed <SEED>    seed of every random decision [default: 13U2x]
+  --map <PATH>     map file to play, relative to the assets folder (e.g. maps/crossroads.map.ron)
+  --teams <COUNT>  number of teams of the default map, between 2 and 8 [default: 3]
+  --headless       runs without window, renderer nor audio
+  --ticks <COUNT>  stops the match after this many ticks (headless only)
+  --until-castle-falls
+                   stops the match when the first castle falls (headless only)
+  --log <FILTER>   log filter, e.g. \"info,game::racks=debug\"
+  --record <PATH>  records the seed and local player inputs into a replay file
+  --replay <PATH>  plays a replay file instead of live inputs
+  --load <PATH>    starts from a snapshot saved with F5
+  -h, --help       prints this help";
+
+const MIN_TEAMS: usize = 2;
+const MAX_TEAMS: usize = 8;
+const DEFAULT_TEAMS: usize = 3;
+
+/// How the game is launched, read by plugins at startup.
+#[derive(Resource, Clone, Debug)]
+pub struct GameConfig {
+    pub seed: String,
+    pub map: Option<String>,
+    pub teams: usize,
+    pub headless: bool,
+    pub ticks: Option<u32>,
+    pub until_castle_falls: bool,
+    pub log: String,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub load: Option<String>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            seed: DEFAULT_SEED.to_string(),
+            map: None,
+            teams: DEFAULT_TEAMS,
+            headless: false,
+            ticks: None,
+            until_castle_falls: false,
+            log: DEFAULT_LOG_FILTER.to_string(),
+            record: None,
+            replay: None,
+            load: None,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Parses command-line arguments (without the program name).
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => config.seed = value(&mut args, &arg)?,
+                "--map" => config.map = Some(value(&mut args, &arg)?),
+                "--teams" => config.teams = parse(&mut args, &arg)?,
+                "--headless" => config.headless = true,
+                "--ticks" => config.ticks = Some(parse(&mut args, &arg)?),
+                "--until-castle-falls" => config.until_castle_falls = true,
+                "--log" => config.log = value(&mut args, &arg)?,
+                "--record" => config.record = Some(value(&mut args, &arg)?),
+                "--replay" => config.replay = Some(value(&mut args, &arg)?),
+                "--load" => config.load = Some(value(&mut args, &arg)?),
+                _ => return Err(format!("unknown argument: {arg}")),
+            }
+        }
+
+        if !(MIN_TEAMS..=MAX_TEAMS).contains(&config.teams) {
+            return Err(format!(
+                "--teams must be between {MIN_TEAMS} and {MAX_TEAMS}, got {}",
+                config.teams
+            ));
+        }
+
+        Ok(config)
+    }
+
+    pub fn seed_u64(&self) -> u64 {
+        xxh3_64(self.seed.as_bytes())
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, name: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for {name}"))
+}
+
+fn parse<T: std::str::FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    name: &str,
+) -> Result<T, String> {
+    let value = value(args, name)?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {name}: {value}"))
+}
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameConfig>()
+            .add_systems(OnExit(GameState::MainMenu), seed_rng.before(MapSet));
+    }
+}
+
+// runs before the match setup forks the global RNG, every match replays the same seed
+fn seed_rng(config: Res<GameConfig>, mut rand: ResMut<GlobalRng>) {
+    info!("seed: {}", config.seed);
+    *rand = GlobalRng::with_seed(config.seed_u64());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn no_argument_gives_the_defaults() {
+        let config = GameConfig::from_args(args("")).unwrap();
+        assert_eq!(config.seed, DEFAULT_SEED);
+        assert_eq!(config.teams, DEFAULT_TEAMS);
+        assert!(!config.headless);
+        assert_eq!(config.ticks, None);
+        assert!(!config.until_castle_falls);
+        assert_eq!(config.log, DEFAULT_LOG_FILTER);
+    }
+
+    #[test]
+    fn full_command_line() {
+        let config = GameConfig::from_args(args(
+            "--seed abc --map maps/crossroads.map.ron --teams 5 --headless --ticks 3600 \
+             --until-castle-falls --log info --record a.replay.ron --replay b.replay.ron \
+             --load snapshot.ron",
+        ))
+        .unwrap();
+
+        assert_eq!(config.seed, "abc");
+        assert_eq!(config.map.as_deref(), Some("maps/crossroads.map.ron"));
+        assert_eq!(config.teams, 5);
+        assert!(config.headless);
+        assert_eq!(config.ticks, Some(3600));
+        assert!(config.until_castle_falls);
+        assert_eq!(config.log, "info");
+        assert_eq!(config.record.as_deref(), Some("a.replay.ron"));
+        assert_eq!(config.replay.as_deref(), Some("b.replay.ron"));
+        assert_eq!(config.load.as_deref(), Some("snapshot.ron"));
+    }
+
+    #[test]
+    fn unknown_arguments_are_rejected() {
+        assert_eq!(
+            GameConfig::from_args(args("--headless --fast")).unwrap_err(),
+            "unknown argument: --fast"
+        );
+        assert!(GameConfig::from_args(args("abc")).is_err());
+    }
+
+    #[test]
+    fn missing_values_are_rejected() {
+        assert_eq!(
+            GameConfig::from_args(args("--seed")).unwrap_err(),
+            "missing value for --seed"
+        );
+        assert_eq!(
+            GameConfig::from_args(args("--headless --ticks")).unwrap_err(),
+            "missing value for --ticks"
+        );
+        assert_eq!(
+            GameConfig::from_args(args("--ticks many")).unwrap_err(),
+            "invalid value for --ticks: many"
+        );
+    }
+
+    #[test]
+    fn teams_must_be_between_2_and_8() {
+        for teams in [MIN_TEAMS, MAX_TEAMS] {
+            let config = GameConfig::from_args(args(&format!("--teams {teams}"))).unwrap();
+            assert_eq!(config.teams, teams);
+        }
+        for teams in ["0", "1", "9", "-1"] {
+            assert!(
+                GameConfig::from_args(args(&format!("--teams {teams}"))).is_err(),
+                "--teams {teams} accepted"
+            );
+        }
+    }
+}
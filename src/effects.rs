@@ -0,0 +1,291 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::GameSet,
+    health::{DamageEvent, DamageKind, Dead},
+    teams::TeamId,
+};
+
+/// Burns stacking on the same entity, the oldest one is dropped past it.
+pub const MAX_BURN_STACKS: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Effect {
+    /// damage per second, stacks up to `MAX_BURN_STACKS`
+    Burn(f32),
+    /// share of the speed lost (0 to 1), the strongest slow applies
+    Slow(f32),
+    /// no movement, no action, no spawn
+    Stun,
+    /// absorbs this much damage before health, stacks
+    Shield(f32),
+}
+
+impl Effect {
+    fn same_kind(&self, other: &Effect) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StatusEffect {
+    pub effect: Effect,
+    pub remaining_secs: f32,
+    /// who applied it, burns are dealt by them
+    pub source: Option<Entity>,
+    pub team: Option<TeamId>,
+}
+
+/// Effects currently affecting an entity, added with the first `ApplyEffect`.
+#[derive(Component, Default, Clone, Debug)]
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.effects.iter()
+    }
+
+    /// Adds an effect following its stacking rule.
+    pub fn add(&mut self, effect: StatusEffect) {
+        match effect.effect {
+            Effect::Burn(_) => {
+                let burns = self
+                    .effects
+                    .iter()
+                    .filter(|current| current.effect.same_kind(&effect.effect))
+                    .count();
+                if burns >= MAX_BURN_STACKS {
+                    let oldest = self
+                        .effects
+                        .iter()
+                        .position(|current| current.effect.same_kind(&effect.effect))
+                        .unwrap();
+                    self.effects.remove(oldest);
+                }
+                self.effects.push(effect);
+            }
+            Effect::Shield(_) => self.effects.push(effect),
+            // slows and stuns do not stack, the strongest and longest one is kept
+            Effect::Slow(_) | Effect::Stun => {
+                match self
+                    .effects
+                    .iter_mut()
+                    .find(|current| current.effect.same_kind(&effect.effect))
+                {
+                    Some(current) => {
+                        if let (Effect::Slow(current_slow), Effect::Slow(slow)) =
+                            (&mut current.effect, effect.effect)
+                        {
+                            *current_slow = current_slow.max(slow);
+                        }
+                        current.remaining_secs = current.remaining_secs.max(effect.remaining_secs);
+                    }
+                    None => self.effects.push(effect),
+                }
+            }
+        }
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.effects
+            .iter()
+            .any(|current| current.effect == Effect::Stun)
+    }
+
+    /// Multiplies speeds and timers: 0 when stunned, 1 without slow.
+    pub fn speed_factor(&self) -> f32 {
+        if self.is_stunned() {
+            return 0.;
+        }
+        let slow = self
+            .effects
+            .iter()
+            .filter_map(|current| match current.effect {
+                Effect::Slow(slow) => Some(slow),
+                _ => None,
+            })
+            .fold(0., f32::max);
+        (1. - slow).clamp(0., 1.)
+    }
+
+    /// Damage left once shields absorbed what they can, used shields are removed.
+    pub fn absorb(&mut self, mut amount: f32) -> f32 {
+        for current in &mut self.effects {
+            if let Effect::Shield(shield) = &mut current.effect {
+                let absorbed = shield.min(amount);
+                *shield -= absorbed;
+                amount -= absorbed;
+            }
+        }
+        self.effects
+            .retain(|current| !matches!(current.effect, Effect::Shield(shield) if shield <= 0.));
+        amount
+    }
+}
+
+/// Factor to apply to speeds and timers of an entity which may have effects.
+pub fn speed_factor(effects: Option<&StatusEffects>) -> f32 {
+    effects.map_or(1., StatusEffects::speed_factor)
+}
+
+/// Effect to add to `target`, applied in `GameSet::Damage` and active from the next tick.
+#[derive(Event, Clone, Debug)]
+pub struct ApplyEffect {
+    pub target: Entity,
+    pub effect: Effect,
+    pub duration_secs: f32,
+    pub source: Option<Entity>,
+    pub team: Option<TeamId>,
+}
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyEffect>().add_systems(
+            FixedUpdate,
+            (
+                tick_effects.in_set(GameSet::Logic),
+                apply_effects.in_set(GameSet::Damage),
+            ),
+        );
+    }
+}
+
+fn apply_effects(
+    mut commands: Commands,
+    mut effect_events: EventReader<ApplyEffect>,
+    mut query: Query<Option<&mut StatusEffects>, Without<Dead>>,
+) {
+    // entities getting their first effects this tick
+    let mut added: Vec<(Entity, StatusEffects)> = vec![];
+
+    for event in effect_events.read() {
+        let effect = StatusEffect {
+            effect: event.effect,
+            remaining_secs: event.duration_secs,
+            source: event.source,
+            team: event.team,
+        };
+        match query.get_mut(event.target) {
+            Ok(Some(mut effects)) => effects.add(effect),
+            Ok(None) => match added.iter_mut().find(|(entity, _)| *entity == event.target) {
+                Some((_, effects)) => effects.add(effect),
+                None => {
+                    let mut effects = StatusEffects::default();
+                    effects.add(effect);
+                    added.push((event.target, effects));
+                }
+            },
+            Err(_) => {}
+        }
+    }
+
+    for (entity, effects) in added {
+        commands.entity(entity).insert(effects);
+    }
+}
+
+fn tick_effects(
+    time: Res<Time>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut query: Query<(Entity, &mut StatusEffects), Without<Dead>>,
+) {
+    for (entity, mut effects) in &mut query {
+        for current in &mut effects.effects {
+            if let Effect::Burn(per_sec) = current.effect {
+                damage_events.send(DamageEvent {
+                    target: entity,
+                    source: current.source,
+                    team: current.team,
+                    amount: per_sec * time.delta_seconds(),
+                    kind: DamageKind::Burn,
+                });
+            }
+            current.remaining_secs -= time.delta_seconds();
+        }
+        effects
+            .effects
+            .retain(|current| current.remaining_secs > 0.);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(effect: Effect, remaining_secs: f32) -> StatusEffect {
+        StatusEffect {
+            effect,
+            remaining_secs,
+            source: None,
+            team: None,
+        }
+    }
+
+    #[test]
+    fn burns_stack_up_to_the_limit() {
+        let mut effects = StatusEffects::default();
+        for index in 0..MAX_BURN_STACKS + 2 {
+            effects.add(status(Effect::Burn(index as f32), 1.));
+        }
+
+        let burns: Vec<Effect> = effects.iter().map(|current| current.effect).collect();
+        assert_eq!(burns.len(), MAX_BURN_STACKS);
+        // the oldest ones were dropped
+        assert_eq!(burns[0], Effect::Burn(2.));
+        assert_eq!(burns[MAX_BURN_STACKS - 1], Effect::Burn(6.));
+    }
+
+    #[test]
+    fn slows_keep_the_strongest_and_longest() {
+        let mut effects = StatusEffects::default();
+        effects.add(status(Effect::Slow(0.5), 1.));
+        effects.add(status(Effect::Slow(0.2), 3.));
+
+        let slows: Vec<&StatusEffect> = effects.iter().collect();
+        assert_eq!(slows.len(), 1);
+        assert_eq!(slows[0].effect, Effect::Slow(0.5));
+        assert_eq!(slows[0].remaining_secs, 3.);
+        assert_eq!(effects.speed_factor(), 0.5);
+    }
+
+    #[test]
+    fn stuns_do_not_stack_and_stop_everything() {
+        let mut effects = StatusEffects::default();
+        effects.add(status(Effect::Slow(0.5), 1.));
+        effects.add(status(Effect::Stun, 2.));
+        effects.add(status(Effect::Stun, 1.));
+
+        assert_eq!(effects.iter().count(), 2);
+        assert!(effects.is_stunned());
+        assert_eq!(effects.speed_factor(), 0.);
+    }
+
+    #[test]
+    fn speed_factor_is_one_without_slow() {
+        let mut effects = StatusEffects::default();
+        assert_eq!(effects.speed_factor(), 1.);
+        effects.add(status(Effect::Shield(10.), 1.));
+        assert_eq!(effects.speed_factor(), 1.);
+        assert_eq!(speed_factor(None), 1.);
+    }
+
+    #[test]
+    fn shields_absorb_in_order_and_are_removed_once_used() {
+        let mut effects = StatusEffects::default();
+        effects.add(status(Effect::Shield(10.), 1.));
+        effects.add(status(Effect::Shield(5.), 1.));
+
+        assert_eq!(effects.absorb(12.), 0.);
+        let shields: Vec<Effect> = effects.iter().map(|current| current.effect).collect();
+        assert_eq!(shields, vec![Effect::Shield(3.)]);
+
+        assert_eq!(effects.absorb(8.), 5.);
+        assert_eq!(effects.iter().count(), 0);
+        assert_eq!(effects.absorb(4.), 4.);
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use bevy::{
+    app::AppExit, asset::AssetPlugin, hierarchy::HierarchyPlugin, prelude::*,
+    time::TimeUpdateStrategy, transform::TransformPlugin,
+};
+
+use crate::{
+    common::Tick,
+    physics::TICK_RATE,
+    state::AutoStart,
+    stats::TeamStats,
+    teams::Teams,
+    victory::{MatchEnded, TeamEliminated},
+};
+
+/// Runs the game without window, renderer nor audio.
+/// The match stops after `ticks` fixed updates or as soon as it is won,
+/// or as soon as a castle falls with `until_castle_falls`.
+pub struct HeadlessPlugin {
+    pub ticks: Option<u32>,
+    pub until_castle_falls: bool,
+}
+
+#[derive(Resource)]
+pub struct HeadlessRun {
+    pub max_ticks: Option<u32>,
+    pub until_castle_falls: bool,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            AssetPlugin::default(),
+        ))
+        // bundles still create meshes and materials, there is just nothing to draw them
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        // every update simulates exactly one tick, whatever the machine speed
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1. / TICK_RATE,
+        )))
+        .insert_resource(HeadlessRun {
+            max_ticks: self.ticks,
+            until_castle_falls: self.until_castle_falls,
+        })
+        // nobody is there to leave the main menu
+        .insert_resource(AutoStart)
+        // before anything saved on exit in `Last`, like replays
+        .add_systems(PostUpdate, stop_match);
+    }
+}
+
+fn stop_match(
+    run: Res<HeadlessRun>,
+    tick: Res<Tick>,
+    stats: Res<TeamStats>,
+    teams: Option<Res<Teams>>,
+    mut ended_events: EventReader<MatchEnded>,
+    mut eliminated_events: EventReader<TeamEliminated>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let ended = ended_events.read().next().is_some();
+    // reads every event, none is left for the next update
+    let fallen = eliminated_events
+        .read()
+        .last()
+        .filter(|_| run.until_castle_falls)
+        .map(|event| event.team);
+    let timeout = run
+        .max_ticks
+        .is_some_and(|max_ticks| tick.0 >= u64::from(max_ticks));
+    if !ended && fallen.is_none() && !timeout {
+        return;
+    }
+
+    if ended {
+        info!("[headless] match won after {} ticks", tick.0);
+    } else if let Some(team) = fallen {
+        let name = teams
+            .as_ref()
+            .map_or_else(|| team.0.to_string(), |teams| teams.get(team).name.clone());
+        info!(
+            "[headless] castle of team {} fell after {} ticks",
+            name, tick.0
+        );
+    } else {
+        info!("[headless] match stopped after {} ticks", tick.0);
+    }
+
+    if let Some(teams) = teams {
+        for (id, stat) in stats.iter() {
+            info!(
+                "[headless] team {}: {} minions spawned, {} lost, {} racks built, {} destroyed, \
+                 {:.0} damage dealt, {:.0} taken, {:.0} gold earned, {:.0} spent",
+                teams.get(id).name,
+                stat.minions_spawned,
+                stat.minions_lost,
+                stat.racks_built,
+                stat.racks_destroyed,
+                stat.damage_dealt,
+                stat.damage_taken,
+                stat.gold_earned,
+                stat.gold_spent,
+            );
+        }
+    }
+
+    exit.send(AppExit);
+}
@@ -1,8 +1,11 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{common::GameSet, effects::StatusEffects, teams::TeamId};
 
 pub const DEFAULT_HEALTH_COLOR: Color = Color::rgb(0.2, 0.8, 0.2);
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Health {
     pub value: f32,
     pub max: f32,
@@ -10,6 +13,10 @@ pub struct Health {
     pub add_health_bar: bool,
     pub health_bar_size: Option<Vec2>,
     pub health_bar_position: Option<Vec3>,
+
+    /// who dealt the killing blow, if known
+    #[serde(skip)]
+    pub killer: Option<Entity>,
 }
 
 impl Health {
@@ -20,6 +27,7 @@ impl Health {
             add_health_bar: true,
             health_bar_position: None,
             health_bar_size: None,
+            killer: None,
         }
     }
 
@@ -44,11 +52,153 @@ impl Health {
         self
     }
 
+    /// Gives back health, up to `max`. The dead stay dead.
+    pub fn heal(&mut self, value: f32) -> &Self {
+        if value < 0. || self.is_dead() {
+            return self;
+        }
+        self.value = (self.value + value).min(self.max);
+        self
+    }
+
     pub fn is_dead(&self) -> bool {
         self.value <= 0.
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DamageKind {
+    /// swords
+    Physical,
+    /// minion explosions
+    Explosive,
+    /// minions losing life over time
+    Decay,
+    /// burning status effects
+    Burn,
+    /// ignores resistances and shields
+    True,
+}
+
+/// Share of the damage resisted, by kind: `0.5` halves it, `-0.5` deals 50% more.
+///
+/// Entities without it take damage as is.
+#[derive(Component, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Resistances {
+    pub physical: f32,
+    pub explosive: f32,
+    pub decay: f32,
+    pub burn: f32,
+}
+
+impl Resistances {
+    /// Damage actually dealt once resisted, never negative.
+    pub fn apply(&self, kind: DamageKind, amount: f32) -> f32 {
+        let resistance = match kind {
+            DamageKind::Physical => self.physical,
+            DamageKind::Explosive => self.explosive,
+            DamageKind::Decay => self.decay,
+            DamageKind::Burn => self.burn,
+            DamageKind::True => return amount,
+        };
+        (amount * (1. - resistance)).max(0.)
+    }
+}
+
+/// Damage to deal, every damage goes through this event and is applied in `GameSet::Damage`.
+///
+/// `amount` is the raw damage, the target's `Resistances` and shields are applied with it.
+#[derive(Event, Clone, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    /// the entity dealing the damage (a player for swords, an explosion, etc)
+    pub source: Option<Entity>,
+    /// the team dealing the damage
+    pub team: Option<TeamId>,
+    pub amount: f32,
+    pub kind: DamageKind,
+}
+
+impl DamageEvent {
+    /// Kills `target` without any killer: old age, leaving the world, etc.
+    pub fn lethal(target: Entity) -> Self {
+        Self {
+            target,
+            source: None,
+            team: None,
+            amount: f32::MAX,
+            kind: DamageKind::True,
+        }
+    }
+
+    /// Sent by `lethal`: a death, not damage taken in a fight.
+    pub fn is_lethal(&self) -> bool {
+        self.source.is_none() && self.kind == DamageKind::True
+    }
+}
+
+/// Health to give back, applied in `GameSet::Damage` before damage.
+#[derive(Event, Clone, Debug)]
+pub struct HealEvent {
+    pub target: Entity,
+    /// the entity healing, `None` for regeneration
+    pub source: Option<Entity>,
+    pub amount: f32,
+}
+
+/// Heals every second, once out of combat for `delay_secs`.
+#[derive(Component, Clone, Copy, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Regeneration {
+    /// health per second
+    pub flat: f32,
+    /// share of the max health per second
+    pub percent: f32,
+    /// seconds without taking damage before regenerating
+    pub delay_secs: f32,
+    /// seconds since the last damage taken
+    #[serde(skip)]
+    pub idle_secs: f32,
+}
+
+impl Regeneration {
+    pub fn per_sec(&self, health: &Health) -> f32 {
+        self.flat + self.percent * health.max
+    }
+}
+
+/// Damage once applied: `amount` is the health actually lost.
+#[derive(Event, Clone, Debug)]
+pub struct Damaged {
+    pub damage: DamageEvent,
+    pub amount: f32,
+    /// this damage killed the target
+    pub killed: bool,
+}
+
+/// Sent once per death, when the health of an entity reaches zero.
+#[derive(Event, Clone, Debug)]
+pub struct Died {
+    pub entity: Entity,
+    /// who dealt the killing blow, if known
+    pub killer: Option<Entity>,
+    /// team of the dead entity
+    pub team: Option<TeamId>,
+}
+
+/// Added to dead entities, they stay in the world unless they despawn on death.
+#[derive(Component)]
+pub struct Dead;
+
+/// Despawns the entity (and its children) once dead.
+#[derive(Component, Clone, Copy, Default)]
+pub struct DespawnOnDeath;
+
+/// Delays `DespawnOnDeath`, e.g. for death animations.
+#[derive(Component, Clone)]
+pub struct DeathDelay(pub Timer);
+
 #[derive(Component)]
 pub struct HealthBar {
     pub entity: Entity,
@@ -87,14 +237,137 @@ pub struct HealthPlugin;
 
 impl Plugin for HealthPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PreUpdate, add_health_bars).add_systems(
-            PostUpdate,
-            (
-                update_health_bar_position,
-                update_health_bar_visual,
-                clear_orphans_healthbars,
-            ),
-        );
+        app.add_event::<DamageEvent>()
+            .add_event::<HealEvent>()
+            .add_event::<Damaged>()
+            .add_event::<Died>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    regenerate.in_set(GameSet::Logic),
+                    (apply_heal, apply_damage, kill)
+                        .chain()
+                        .in_set(GameSet::Damage),
+                    despawn_dead.in_set(GameSet::Cleanup),
+                ),
+            )
+            .add_systems(PreUpdate, add_health_bars)
+            .add_systems(
+                PostUpdate,
+                (
+                    update_health_bar_position,
+                    update_health_bar_visual,
+                    clear_orphans_healthbars,
+                ),
+            );
+    }
+}
+
+fn regenerate(
+    time: Res<Time>,
+    mut heal_events: EventWriter<HealEvent>,
+    mut query: Query<(Entity, &Health, &mut Regeneration), Without<Dead>>,
+) {
+    for (entity, health, mut regeneration) in &mut query {
+        regeneration.idle_secs += time.delta_seconds();
+        if regeneration.idle_secs < regeneration.delay_secs || health.value >= health.max {
+            continue;
+        }
+
+        heal_events.send(HealEvent {
+            target: entity,
+            source: None,
+            amount: regeneration.per_sec(health) * time.delta_seconds(),
+        });
+    }
+}
+
+fn apply_heal(mut heal_events: EventReader<HealEvent>, mut query: Query<&mut Health>) {
+    for heal in heal_events.read() {
+        if let Ok(mut health) = query.get_mut(heal.target) {
+            health.heal(heal.amount);
+        }
+    }
+}
+
+fn apply_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    mut damaged_events: EventWriter<Damaged>,
+    mut query: Query<(
+        &mut Health,
+        Option<&Resistances>,
+        Option<&mut Regeneration>,
+        Option<&mut StatusEffects>,
+    )>,
+) {
+    for damage in damage_events.read() {
+        let Ok((mut health, resistances, regeneration, effects)) = query.get_mut(damage.target)
+        else {
+            continue;
+        };
+        // already dead, the killer is known
+        if health.is_dead() {
+            continue;
+        }
+
+        let amount = match resistances {
+            Some(resistances) => resistances.apply(damage.kind, damage.amount),
+            None => damage.amount,
+        };
+        let amount = match effects {
+            Some(mut effects) if damage.kind != DamageKind::True => effects.absorb(amount),
+            _ => amount,
+        };
+        if let Some(mut regeneration) = regeneration {
+            if amount > 0. {
+                regeneration.idle_secs = 0.;
+            }
+        }
+        let before = health.value;
+        let killed = health.hit(amount).is_dead();
+        if killed {
+            health.killer = damage.source;
+        }
+
+        damaged_events.send(Damaged {
+            damage: damage.clone(),
+            amount: before - health.value,
+            killed,
+        });
+    }
+}
+
+// every death goes through here, whatever dealt the damage
+fn kill(
+    mut commands: Commands,
+    mut died_events: EventWriter<Died>,
+    query: Query<(Entity, &Health, Option<&TeamId>), Without<Dead>>,
+) {
+    for (entity, health, team) in &query {
+        if health.is_dead() {
+            commands.entity(entity).insert(Dead);
+            died_events.send(Died {
+                entity,
+                killer: health.killer,
+                team: team.copied(),
+            });
+        }
+    }
+}
+
+// death hooks read `Died` in `GameSet::Cleanup`, despawned entities are only gone after it
+fn despawn_dead(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, Option<&mut DeathDelay>), (With<Dead>, With<DespawnOnDeath>)>,
+) {
+    for (entity, delay) in &mut query {
+        if let Some(mut delay) = delay {
+            if !delay.0.tick(time.delta()).finished() {
+                continue;
+            }
+        }
+        commands.entity(entity).despawn_recursive();
     }
 }
 
@@ -0,0 +1,94 @@
+pub mod audio;
+pub mod balance;
+pub mod castles;
+pub mod common;
+pub mod config;
+pub mod effects;
+pub mod headless;
+pub mod health;
+pub mod maps;
+pub mod menu;
+pub mod minions;
+pub mod navigation;
+pub mod physics;
+pub mod player;
+pub mod racks;
+pub mod replay;
+pub mod snapshot;
+pub mod spatial;
+pub mod state;
+pub mod stats;
+pub mod teams;
+pub mod victory;
+
+use audio::AudioPlugin;
+use balance::BalancePlugin;
+use bevy::{app::PluginGroupBuilder, prelude::*};
+use bevy_cameraman::CameraPlugin;
+use bevy_turborand::prelude::*;
+use config::ConfigPlugin;
+use effects::EffectsPlugin;
+use health::HealthPlugin;
+use maps::MapsPlugin;
+use menu::MenuPlugin;
+use minions::MinionsPlugin;
+use navigation::NavigationPlugin;
+use physics::PhysicsPlugin;
+use player::{LocalPlayerPlugin, PlayerPlugin};
+use racks::RacksPlugin;
+use replay::ReplayPlugin;
+use snapshot::SnapshotPlugin;
+use spatial::SpatialPlugin;
+use state::GameStatePlugin;
+use stats::StatsPlugin;
+use victory::VictoryPlugin;
+
+/// Every plugin making the game, configured by the `GameConfig` resource.
+///
+/// Plugins can be configured or disabled like any bevy plugin group:
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use game::{audio::AudioPlugin, GamePlugins};
+/// App::new()
+///     .add_plugins((DefaultPlugins, GamePlugins.build().disable::<AudioPlugin>()))
+///     .run();
+/// ```
+pub struct GamePlugins;
+
+impl GamePlugins {
+    /// Plugins without anything needing a window, a renderer or an audio device.
+    pub fn headless(self) -> PluginGroupBuilder {
+        self.build()
+            .disable::<LocalPlayerPlugin>()
+            .disable::<MenuPlugin>()
+            .disable::<AudioPlugin>()
+            .disable::<CameraPlugin>()
+    }
+}
+
+impl PluginGroup for GamePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(ConfigPlugin)
+            .add(GameStatePlugin)
+            .add(BalancePlugin)
+            .add(RngPlugin::new())
+            .add(PhysicsPlugin)
+            .add(MapsPlugin)
+            .add(SpatialPlugin)
+            .add(NavigationPlugin)
+            .add(MinionsPlugin)
+            .add(RacksPlugin)
+            .add(HealthPlugin)
+            .add(EffectsPlugin)
+            .add(VictoryPlugin)
+            .add(StatsPlugin)
+            .add(PlayerPlugin)
+            .add(LocalPlayerPlugin)
+            .add(MenuPlugin)
+            .add(ReplayPlugin)
+            .add(SnapshotPlugin)
+            .add(AudioPlugin)
+            .add(CameraPlugin)
+    }
+}
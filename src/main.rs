@@ -1,78 +1,69 @@
-mod audio;
-mod castles;
-mod common;
-mod health;
-mod minions;
-mod physics;
-mod player;
-mod racks;
-mod teams;
-
-use audio::AudioPlugin;
 use bevy::{
     log::{Level, LogPlugin},
     prelude::*,
     DefaultPlugins,
 };
-use bevy_cameraman::CameraPlugin;
-use bevy_rapier2d::prelude::*;
-use bevy_turborand::prelude::*;
-use castles::CastlesPlugin;
-use health::HealthPlugin;
-use minions::MinionsPlugin;
-use physics::PhysicsPlugin;
-use player::LocalPlayerPlugin;
-use racks::RacksPlugin;
-use teams::TeamsPlugin;
-use xxhash_rust::xxh3::xxh3_64;
+use game::{
+    config::{GameConfig, USAGE},
+    headless::HeadlessPlugin,
+    replay::{Replay, ReplayPlayback},
+    GamePlugins,
+};
 
 const AUDIO_SCALE: f32 = 1. / 100.0;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return;
+    }
+
+    let mut config = match GameConfig::from_args(args) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
     let mut app = App::new();
-    let seed = b"13U2x";
 
-    app.add_plugins((
-        DefaultPlugins
-            .set(LogPlugin {
-                level: Level::TRACE,
-                filter: [
-                    "wgpu=error",
-                    "bevy_render=warn,bevy_app=warn,bevy_ecs=warn",
-                    "naga=warn",
-                    "gilrs=warn",
-                    "game::health=info,game::racks=info",
-                ]
-                .join(","),
-            })
-            .set(bevy::audio::AudioPlugin {
+    if let Some(path) = &config.replay {
+        let replay = match Replay::load(path) {
+            Ok(replay) => replay,
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                std::process::exit(2);
+            }
+        };
+        replay.configure(&mut config);
+        app.insert_resource(ReplayPlayback::new(replay));
+    }
+
+    let log = LogPlugin {
+        level: Level::TRACE,
+        filter: config.log.clone(),
+    };
+
+    if config.headless {
+        app.add_plugins((
+            HeadlessPlugin {
+                ticks: config.ticks,
+                until_castle_falls: config.until_castle_falls,
+            },
+            log,
+            GamePlugins.headless(),
+        ));
+    } else {
+        app.add_plugins((
+            DefaultPlugins.set(log).set(bevy::audio::AudioPlugin {
                 spatial_scale: bevy::audio::SpatialScale::new_2d(AUDIO_SCALE),
                 ..default()
             }),
-        RngPlugin::new().with_rng_seed(xxh3_64(seed)),
-        PhysicsPlugin,
-        TeamsPlugin,
-        MinionsPlugin,
-        RacksPlugin,
-        CastlesPlugin,
-        HealthPlugin,
-        LocalPlayerPlugin,
-        AudioPlugin,
-    ))
-    // --- camera ---
-    .add_plugins((
-        CameraPlugin,
-        // CameraDebugPlugin,
-    ))
-    // --- physics ---
-    .add_plugins((
-        RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.),
-        // RapierDebugRenderPlugin::default(),
-    ))
-    // removes gravity
-    .insert_resource(RapierConfiguration {
-        gravity: Vec2::new(0.0, 0.0),
-        ..default()
-    })
-    .run();
+            GamePlugins,
+        ));
+    }
+
+    app.insert_resource(config).run();
 }
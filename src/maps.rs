@@ -0,0 +1,345 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState},
+    prelude::*,
+    reflect::TypePath,
+    sprite::{Sprite, SpriteBundle},
+    utils::BoxedFuture,
+};
+use bevy_rapier2d::prelude::*;
+use bevy_turborand::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    balance::Balance,
+    castles::CastleBundle,
+    common::{Tick, WorldBounds, GAME_MAX_HEIGHT, GAME_MAX_WIDTH},
+    config::GameConfig,
+    minions::MinionKind,
+    navigation::NavObstacle,
+    racks::RackBundle,
+    state::{GameState, MatchEntity},
+    teams::{Relation, Teams},
+};
+
+const OBSTACLE_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+
+const ARENA_CASTLE_RADIUS: f32 = 600.;
+const ARENA_FIRST_ANGLE: f32 = PI * 1.25;
+// blue
+const ARENA_FIRST_HUE: f32 = 240.;
+
+/// "a", "b", "c", ...
+fn team_name(index: usize) -> String {
+    char::from(b'a' + index as u8).to_string()
+}
+
+/// A battlefield: its teams, their castles and racks, obstacles and the world bounds.
+///
+/// Written in RON (`assets/maps/*.map.ron`) and played with `--map maps/<name>.map.ron`.
+/// The map is spawned every time a match starts.
+#[derive(Asset, Resource, TypePath, Deserialize, Clone, Debug)]
+pub struct Map {
+    /// width and height of the world, centered on the origin
+    pub size: Vec2,
+    pub teams: Vec<MapTeam>,
+    #[serde(default)]
+    pub obstacles: Vec<MapObstacle>,
+    /// racks scattered around the origin for every team (spawning random kinds of minions),
+    /// on top of pre-placed ones (spawning kamikazes)
+    #[serde(default)]
+    pub random_racks: Option<RandomRacks>,
+    /// alliances and neutral teams, teams not listed here are hostile to each other
+    #[serde(default)]
+    pub relations: Vec<(String, String, Relation)>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct MapTeam {
+    /// name of the team, used by `relations`
+    pub id: String,
+    /// red, green, blue
+    pub color: (f32, f32, f32),
+    pub castle: Vec2,
+    #[serde(default)]
+    pub racks: Vec<Vec2>,
+    /// the local player joins the first team having a player position
+    #[serde(default)]
+    pub player: Option<Vec2>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapObstacle {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct RandomRacks {
+    /// racks per team, from `min` (inclusive) to `max` (exclusive)
+    pub min: u32,
+    pub max: u32,
+    /// racks are placed between `-area` and `area`
+    pub area: Vec2,
+}
+
+impl Map {
+    /// The default battlefield: `team_count` teams with castles evenly spread on a circle,
+    /// each with its own color, and randomly scattered racks.
+    pub fn arena(team_count: usize) -> Self {
+        let teams = (0..team_count)
+            .map(|index| {
+                // the first team (the local player's) sits bottom left
+                let angle = ARENA_FIRST_ANGLE + TAU * index as f32 / team_count as f32;
+                let castle = Vec2::from_angle(angle) * ARENA_CASTLE_RADIUS;
+                let [r, g, b, _] = Color::hsl(
+                    (ARENA_FIRST_HUE + 360. * index as f32 / team_count as f32) % 360.,
+                    0.6,
+                    0.5,
+                )
+                .as_rgba_f32();
+
+                MapTeam {
+                    id: team_name(index),
+                    color: (r, g, b),
+                    castle,
+                    racks: vec![],
+                    player: (index == 0).then_some(castle * 0.5),
+                }
+            })
+            .collect();
+
+        Self {
+            size: Vec2::new(GAME_MAX_WIDTH, GAME_MAX_HEIGHT),
+            teams,
+            obstacles: vec![],
+            random_racks: Some(RandomRacks {
+                min: 2,
+                max: 5,
+                area: Vec2::new(500., 300.),
+            }),
+            relations: vec![],
+        }
+    }
+
+    /// The team the local player joins, and where they spawn.
+    pub fn player(&self) -> Option<(&MapTeam, Vec2)> {
+        self.teams
+            .iter()
+            .find_map(|team| team.player.map(|position| (team, position)))
+    }
+}
+
+#[derive(Component)]
+pub struct Obstacle;
+
+#[derive(Bundle)]
+pub struct ObstacleBundle {
+    pub sprite_bundle: SpriteBundle,
+    pub obstacle: Obstacle,
+    pub match_entity: MatchEntity,
+    pub nav_obstacle: NavObstacle,
+    pub rigid_body: RigidBody,
+    pub collider: Collider,
+}
+
+impl ObstacleBundle {
+    pub fn new(position: Vec2, size: Vec2) -> Self {
+        Self {
+            sprite_bundle: SpriteBundle {
+                sprite: Sprite {
+                    color: OBSTACLE_COLOR,
+                    custom_size: Some(size),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(0.)),
+                ..default()
+            },
+            obstacle: Obstacle,
+            match_entity: MatchEntity,
+            nav_obstacle: NavObstacle,
+            rigid_body: RigidBody::Fixed,
+            collider: Collider::cuboid(size.x / 2., size.y / 2.),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MapLoaderError {
+    #[error("could not read map: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse map: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+pub struct MapLoader;
+
+impl AssetLoader for MapLoader {
+    type Asset = Map;
+    type Settings = ();
+    type Error = MapLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<Map>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct MapHandle(Handle<Map>);
+
+/// The map is spawned in this set (`OnExit(GameState::MainMenu)`), teams and match entities exist after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MapSet;
+
+pub struct MapsPlugin;
+
+impl Plugin for MapsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Map>()
+            .init_asset_loader::<MapLoader>()
+            .init_resource::<WorldBounds>()
+            .add_systems(Startup, load)
+            .add_systems(
+                PreUpdate,
+                insert_loaded.run_if(resource_exists::<MapHandle>()),
+            )
+            .add_systems(OnExit(GameState::MainMenu), spawn.in_set(MapSet));
+    }
+}
+
+fn load(mut commands: Commands, config: Res<GameConfig>, server: Res<AssetServer>) {
+    match &config.map {
+        Some(path) => commands.insert_resource(MapHandle(server.load(path.clone()))),
+        None => commands.insert_resource(Map::arena(config.teams)),
+    }
+}
+
+fn insert_loaded(
+    mut commands: Commands,
+    config: Res<GameConfig>,
+    handle: Res<MapHandle>,
+    server: Res<AssetServer>,
+    assets: Res<Assets<Map>>,
+    mut events: EventReader<AssetEvent<Map>>,
+) {
+    if server.get_load_state(handle.0.id()) == Some(LoadState::Failed) {
+        error!(
+            "[maps] could not load {:?}, playing the default arena",
+            config.map
+        );
+        commands.remove_resource::<MapHandle>();
+        commands.insert_resource(Map::arena(config.teams));
+        return;
+    }
+
+    for event in events.read() {
+        if let AssetEvent::LoadedWithDependencies { id } = event {
+            if *id != handle.0.id() {
+                continue;
+            }
+            if let Some(map) = assets.get(*id) {
+                info!("[maps] loaded");
+                let mut map = map.clone();
+                if let Some(random_racks) = &map.random_racks {
+                    if random_racks.min >= random_racks.max {
+                        warn!(
+                            "[maps] random racks need min < max, got {}..{}, none are placed",
+                            random_racks.min, random_racks.max
+                        );
+                        map.random_racks = None;
+                    }
+                }
+                commands.insert_resource(map);
+            }
+        }
+    }
+}
+
+fn spawn(
+    mut commands: Commands,
+    map: Res<Map>,
+    balance: Res<Balance>,
+    mut rand: ResMut<GlobalRng>,
+) {
+    // team ids follow the map order
+    let mut teams = Teams::new();
+    for map_team in &map.teams {
+        let (r, g, b) = map_team.color;
+        teams.add(map_team.id.clone(), Color::rgb(r, g, b));
+    }
+    for (a, b, relation) in &map.relations {
+        match (teams.find(a), teams.find(b)) {
+            (Some(a), Some(b)) => {
+                teams.set_relation(a, b, *relation);
+            }
+            _ => warn!("[maps] unknown team in relation {} / {}", a, b),
+        }
+    }
+
+    for (team, map_team) in teams.iter().zip(&map.teams) {
+        commands.spawn(CastleBundle::new(
+            team,
+            Transform::from_translation(map_team.castle.extend(0.)),
+            RngComponent::from(&mut rand),
+            &balance,
+        ));
+
+        for position in &map_team.racks {
+            commands.spawn(RackBundle::new(
+                team,
+                Transform::from_translation(position.extend(0.)),
+                RngComponent::from(&mut rand),
+                &balance,
+            ));
+        }
+    }
+
+    if let Some(random_racks) = &map.random_racks {
+        let rand_count = rand.get_mut().fork();
+        for team in teams.iter() {
+            let count = rand_count.u32(random_racks.min..random_racks.max);
+            let rand_pos = rand_count.fork();
+            for _ in 0..count {
+                let kind = MinionKind::ALL[rand_pos.usize(0..MinionKind::ALL.len())];
+                commands.spawn(
+                    RackBundle::new(
+                        team,
+                        Transform::from_xyz(
+                            rand_pos.f32_normalized() * random_racks.area.x,
+                            rand_pos.f32_normalized() * random_racks.area.y,
+                            0.,
+                        ),
+                        RngComponent::from(&mut rand),
+                        &balance,
+                    )
+                    .with_minion_kind(kind),
+                );
+            }
+        }
+    }
+
+    for obstacle in &map.obstacles {
+        commands.spawn(ObstacleBundle::new(obstacle.position, obstacle.size));
+    }
+
+    commands.insert_resource(WorldBounds { size: map.size });
+    commands.insert_resource(teams);
+    // the match starts now, whatever time it took to load
+    commands.insert_resource(Tick::default());
+}
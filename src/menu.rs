@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::{
+    player::LocalPlayer,
+    state::GameState,
+    teams::{TeamId, Teams},
+    victory::Winners,
+};
+
+const FONT: &str = "fonts/FiraSans-Regular.ttf";
+
+/// Screens shown outside of a running match, and the keys moving between them.
+pub struct MenuPlugin;
+
+#[derive(Component)]
+struct Screen;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::MainMenu),
+            (setup_camera, setup_screen("Press Enter to play")),
+        )
+        .add_systems(
+            OnEnter(GameState::Paused),
+            setup_screen("Paused\nEscape to resume, Q to quit"),
+        )
+        .add_systems(OnEnter(GameState::GameOver), setup_game_over)
+        .add_systems(OnExit(GameState::MainMenu), clear_screen)
+        .add_systems(OnExit(GameState::Paused), clear_screen)
+        .add_systems(OnExit(GameState::GameOver), clear_screen)
+        .add_systems(Update, navigate);
+    }
+}
+
+// matches bring their own camera
+fn setup_camera(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), Screen));
+}
+
+fn setup_screen(text: &'static str) -> impl Fn(Commands, Res<AssetServer>) {
+    move |mut commands: Commands, asset_server: Res<AssetServer>| {
+        spawn_screen(&mut commands, &asset_server, text);
+    }
+}
+
+fn setup_game_over(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    winners: Option<Res<Winners>>,
+    teams: Option<Res<Teams>>,
+    query_local_player: Query<&TeamId, With<LocalPlayer>>,
+) {
+    let title = match (winners, teams) {
+        (Some(winners), Some(teams)) => match query_local_player.get_single() {
+            Ok(team) if winners.0.contains(team) => "Victory".to_string(),
+            Ok(_) => "Defeat".to_string(),
+            Err(_) => {
+                let names: Vec<&str> = winners
+                    .0
+                    .iter()
+                    .map(|id| teams.get(*id).name.as_str())
+                    .collect();
+                format!("Team {} wins", names.join(", "))
+            }
+        },
+        _ => "Game over".to_string(),
+    };
+
+    spawn_screen(
+        &mut commands,
+        &asset_server,
+        format!("{title}\nPress Enter to go back to the menu"),
+    );
+}
+
+fn spawn_screen(commands: &mut Commands, asset_server: &AssetServer, text: impl Into<String>) {
+    commands.spawn((
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 50.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_text_alignment(TextAlignment::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(40.),
+            left: Val::Percent(30.),
+            ..default()
+        }),
+        Label,
+        Screen,
+    ));
+}
+
+fn clear_screen(mut commands: Commands, query: Query<Entity, With<Screen>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn navigate(
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let next = match state.get() {
+        GameState::MainMenu if keyboard_input.just_pressed(KeyCode::Return) => GameState::InGame,
+        GameState::InGame if keyboard_input.just_pressed(KeyCode::Escape) => GameState::Paused,
+        GameState::Paused if keyboard_input.just_pressed(KeyCode::Escape) => GameState::InGame,
+        GameState::Paused if keyboard_input.just_pressed(KeyCode::Q) => GameState::MainMenu,
+        GameState::GameOver if keyboard_input.just_pressed(KeyCode::Return) => GameState::MainMenu,
+        _ => return,
+    };
+
+    next_state.set(next);
+}
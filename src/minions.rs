@@ -1,79 +1,155 @@
-use crate::{common::*, health::Health, physics::CollisionEvent, teams::Team};
+use crate::{
+    audio::AudioExplosion,
+    balance::Balance,
+    castles::Castle,
+    common::*,
+    effects::{speed_factor, StatusEffects},
+    health::{DamageEvent, DamageKind, Dead, DespawnOnDeath, HealEvent, Health, Resistances},
+    maps::Obstacle,
+    navigation::{FlowFields, NavGrid, PathCache},
+    physics::{CollisionEvent, Collisions, EntityPair},
+    spatial::SpatialGrid,
+    state::MatchEntity,
+    teams::{Team, TeamId, Teams},
+};
 use bevy::{
     prelude::*,
     sprite::MaterialMesh2dBundle,
-    time::{Time, Timer},
-    utils::{default, HashMap},
+    time::{Time, Timer, TimerMode},
+    utils::default,
 };
 use bevy_rapier2d::prelude::*;
+use bevy_turborand::prelude::*;
+use serde::{Deserialize, Serialize};
 
-const MINION_SCALE: f32 = 190.;
-const DESTROY_MINIONS_AFTER_SECS: f32 = 120.;
-const DECAY_VALUE_PER_SEC: f32 = 10.;
-const REWARDS_GOLD: f32 = 1.;
+pub struct MinionsPlugin;
 
-const EXPLOSION_AUDIO_ID: &str = "sounds/explosion.ogg";
+/// Kind of a minion, its stats live in `Balance::minions`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum MinionKind {
+    /// explodes on contact with a hostile unit
+    #[default]
+    Kamikaze,
+    /// hits the unit it chases once touching it
+    Melee,
+    /// shoots projectiles from `attack_radius`
+    Ranged,
+    /// a slow and sturdy melee
+    Tank,
+    /// heals every other ally within `attack_radius`
+    Healer,
+}
 
-pub struct MinionsPlugin;
+impl MinionKind {
+    pub const ALL: [MinionKind; 5] = [
+        MinionKind::Kamikaze,
+        MinionKind::Melee,
+        MinionKind::Ranged,
+        MinionKind::Tank,
+        MinionKind::Healer,
+    ];
+
+    // stops once in range instead of charging
+    fn keeps_distance(self) -> bool {
+        matches!(self, MinionKind::Ranged | MinionKind::Healer)
+    }
+}
 
-// TODO: move this into common
-#[derive(Component)]
-struct TimeDestroyable {
-    timer: Timer,
+/// What a minion is doing, decided every tick from what it sees within `vision_radius`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MinionState {
+    /// no hostile castle left
+    #[default]
+    Idle,
+    /// follows the team's flow field toward the closest hostile castle, around obstacles
+    March,
+    /// walks toward a hostile unit in sight
+    Chase { target: Entity },
+    /// charges a hostile unit within `attack_radius` (ranged and healers stay there)
+    Attack { target: Entity },
+    /// flees a hostile unit back to its castle (around obstacles), once under `retreat_health`
+    Retreat { from: Entity },
 }
 
-#[derive(Component)]
-struct Minion {
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Minion {
+    kind: MinionKind,
     had_exploded: bool,
+    /// until the next hit, shot or heal
+    cooldown: Timer,
+    // decided again on the first tick after a snapshot is loaded
+    #[serde(skip)]
+    state: MinionState,
+    #[serde(skip)]
+    destination: Option<Vec2>,
 }
 
-#[derive(Resource)]
-struct AudioExplosion(Handle<AudioSource>);
+impl Minion {
+    pub fn kind(&self) -> MinionKind {
+        self.kind
+    }
+
+    pub fn state(&self) -> MinionState {
+        self.state
+    }
+}
 
 impl Plugin for MinionsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_audio)
-            .add_systems(
-                Update,
-                (
-                    update_move_minions,
-                    check_collisions_minions,
-                    decay_life,
-                    explosion_damage,
-                ),
+        app.add_systems(
+            FixedUpdate,
+            ((
+                (update_minion_states, (update_move_minions, attack)).chain(),
+                check_collisions_minions,
+                move_projectiles,
+                projectile_hits,
+                decay_life,
+                explosion_damage,
+                leave_world,
+                destroy_after_timer,
             )
-            .add_systems(PostUpdate, (destroy_minions, destroy_after_timer));
+                .in_set(GameSet::Logic),),
+        );
     }
 }
 
-fn setup_audio(mut commands: Commands, server: Res<AssetServer>) {
-    let handle = server.load(EXPLOSION_AUDIO_ID);
-    commands.insert_resource(AudioExplosion(handle));
-}
-
 #[derive(Bundle)]
 pub struct MinionBundle {
     minion: Minion,
+    match_entity: MatchEntity,
     mesh: MaterialMesh2dBundle<ColorMaterial>,
     // sprite: SpriteBundle,
     health: Health,
+    resistances: Resistances,
+    despawn_on_death: DespawnOnDeath,
     rewards: Rewards,
-    team: Team,
+    team: TeamId,
 
     // physics
     body: RigidBody,
     collider: Collider,
+    velocity: Velocity,
     timer_destroyable: TimeDestroyable,
+
+    // every random decision made by this minion
+    rng: RngComponent,
 }
 
 impl MinionBundle {
     pub fn new(
         meshes: &mut ResMut<Assets<Mesh>>,
         materials: &mut ResMut<Assets<ColorMaterial>>,
+        balance: &Balance,
         translation: Vec3,
-        team: Team,
+        team: &Team,
+        kind: MinionKind,
+        rng: RngComponent,
     ) -> Self {
-        let radius = 6.0;
+        let stats = balance.minions.get(kind);
+        let radius = stats.radius;
+        let mut cooldown = Timer::from_seconds(stats.cooldown_secs, TimerMode::Once);
+        // ready to act as soon as spawned
+        cooldown.set_elapsed(cooldown.duration());
         MinionBundle {
             mesh: MaterialMesh2dBundle {
                 mesh: meshes.add(shape::Circle::new(radius).into()).into(),
@@ -92,19 +168,30 @@ impl MinionBundle {
             //     ..default()
             // },
             minion: Minion {
+                kind,
                 had_exploded: false,
+                cooldown,
+                state: MinionState::default(),
+                destination: None,
+            },
+            match_entity: MatchEntity,
+            health: Health::new(stats.health)
+                .with_health_bar_position(Vec3::new(0.0, radius + 9., 0.1))
+                .with_health_bar_size(Vec2::new(radius * 2. - 2., 5.0)),
+            resistances: stats.resistances,
+            despawn_on_death: DespawnOnDeath,
+            rewards: Rewards {
+                gold: stats.rewards_gold,
             },
-            health: Health::new(20.)
-                .with_health_bar_position(Vec3::new(0.0, 15.0, 0.1))
-                .with_health_bar_size(Vec2::new(10.0, 5.0)),
-            rewards: Rewards { gold: REWARDS_GOLD },
-            team,
+            team: team.id,
             // physics
             body: RigidBody::Dynamic,
             collider: Collider::ball(radius * 0.98),
+            velocity: Velocity::zero(),
             timer_destroyable: TimeDestroyable {
-                timer: Timer::from_seconds(DESTROY_MINIONS_AFTER_SECS, bevy::time::TimerMode::Once),
+                timer: Timer::from_seconds(stats.lifetime_secs, TimerMode::Once),
             },
+            rng,
         }
     }
 }
@@ -118,20 +205,20 @@ struct Explosion {
 struct ExplosionBundle {
     mesh: MaterialMesh2dBundle<ColorMaterial>,
     explosion: Explosion,
-    team: Team,
+    match_entity: MatchEntity,
+    team: TeamId,
     sensor: Sensor,
     collider: Collider,
     timer_destroyable: TimeDestroyable,
-    audio: AudioBundle,
 }
 
 impl ExplosionBundle {
     pub fn new(
         meshes: &mut ResMut<Assets<Mesh>>,
         materials: &mut ResMut<Assets<ColorMaterial>>,
-        audio_asset: &Handle<AudioSource>,
         mut translation: Vec3,
-        team: Team,
+        team: &Team,
+        damage: f32,
     ) -> Self {
         let mut color = team.color;
         color.set_a(0.4);
@@ -144,96 +231,153 @@ impl ExplosionBundle {
                 transform: Transform::from_translation(translation),
                 ..default()
             },
-            explosion: Explosion { damage: 30.0 },
-            team,
+            explosion: Explosion { damage },
+            match_entity: MatchEntity,
+            team: team.id,
             collider: Collider::ball(radius * 0.98),
             sensor: Sensor,
             timer_destroyable: TimeDestroyable {
                 timer: Timer::from_seconds(0.2, bevy::time::TimerMode::Once),
             },
-            audio: AudioBundle {
-                source: audio_asset.clone(),
-                settings: PlaybackSettings::ONCE.with_spatial(true),
-            },
         }
     }
 }
 
-fn update_move_minions(
-    time: Res<Time>,
-    mut query: Query<(&mut Transform, Entity, &Team, Option<&Minion>)>,
+fn spawn_explosion(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    audio_explosion: Option<&AudioExplosion>,
+    translation: Vec3,
+    team: &Team,
+    damage: f32,
 ) {
-    let mut closest_translations: HashMap<Entity, Vec3> = HashMap::new();
-
-    let mut combinations = query.iter_combinations_mut();
-    while let Some(
-        [(transform_a, entity_a, team_a, minion_a), (transform_b, entity_b, team_b, minion_b)],
-    ) = combinations.fetch_next()
-    {
-        if minion_a.is_none() && minion_b.is_none() {
-            continue;
-        }
-
-        if team_a.id == team_b.id {
-            continue;
-        }
-
-        let (minion_transform, minion_entity, target_translation) = if minion_a.is_some() {
-            (transform_a, entity_a, transform_b.translation)
-        } else {
-            (transform_b, entity_b, transform_a.translation)
-        };
+    let mut explosion = commands.spawn(ExplosionBundle::new(
+        meshes,
+        materials,
+        translation,
+        team,
+        damage,
+    ));
+
+    // no sound when running without the audio plugin (headless)
+    if let Some(audio_explosion) = audio_explosion {
+        explosion.insert(AudioBundle {
+            source: audio_explosion.0.clone(),
+            settings: PlaybackSettings::ONCE.with_spatial(true),
+        });
+    }
+}
 
-        // found a translation for this entity
-        // but this is farther combination so we do nothing
-        if let Some(closest_translation) = closest_translations.get(&minion_entity) {
-            if minion_transform
-                .translation
-                .distance_squared(*closest_translation)
-                <= minion_transform
-                    .translation
-                    .distance_squared(target_translation)
+// what minions want to do, from what they see
+fn update_minion_states(
+    balance: Res<Balance>,
+    teams: Res<Teams>,
+    grid: Res<SpatialGrid>,
+    nav_grid: Res<NavGrid>,
+    mut paths: ResMut<PathCache>,
+    query_castles: Query<(&Transform, &TeamId), (With<Castle>, Without<Dead>)>,
+    mut query: Query<(Entity, &mut Minion, &Transform, &TeamId, &Health), Without<Dead>>,
+) {
+    for (entity, mut minion, transform, team, health) in &mut query {
+        let stats = balance.minions.get(minion.kind);
+        let position = transform.translation.truncate();
+        let threat = grid.nearest_hostile(&teams, position, *team, stats.vision_radius);
+
+        let (state, destination) = match threat {
+            Some(threat) if health.value < health.max * stats.retreat_health => {
+                // back to the castle around obstacles, or just away from the threat
+                let home = query_castles
+                    .iter()
+                    .find(|(_, castle_team)| *castle_team == team)
+                    .map(|(castle_transform, _)| castle_transform.translation.truncate());
+                let destination = match home {
+                    Some(home) => paths
+                        .next_waypoint(&nav_grid, position, home)
+                        .unwrap_or(home),
+                    // a new goal every tick, not worth a path
+                    None => position * 2. - threat.position,
+                };
+                (
+                    MinionState::Retreat {
+                        from: threat.entity,
+                    },
+                    Some(destination),
+                )
+            }
+            Some(threat)
+                if position.distance_squared(threat.position)
+                    <= stats.attack_radius * stats.attack_radius =>
             {
-                continue;
+                (
+                    MinionState::Attack {
+                        target: threat.entity,
+                    },
+                    Some(threat.position),
+                )
             }
-        }
+            // units in sight are charged straight
+            Some(threat) => (
+                MinionState::Chase {
+                    target: threat.entity,
+                },
+                Some(threat.position),
+            ),
+            // the way is sampled from the flow field when moving
+            None if query_castles
+                .iter()
+                .any(|(_, castle_team)| teams.is_hostile(*team, *castle_team)) =>
+            {
+                (MinionState::March, None)
+            }
+            None => (MinionState::Idle, None),
+        };
 
-        // we are here if we found a closer combination
-        // so we insert it and move toward this combination
-        closest_translations.insert(minion_entity, target_translation);
+        if state != minion.state {
+            debug!("[minions] {:?} {:?} -> {:?}", entity, minion.state, state);
+            minion.state = state;
+        }
+        minion.destination = destination;
     }
+}
 
-    for (entity, translation) in closest_translations {
-        if let Ok((mut transform, _, _, _)) = query.get_mut(entity) {
-            let normalized_target_position = (translation - transform.translation).normalize();
+fn update_move_minions(
+    time: Res<Time>,
+    balance: Res<Balance>,
+    nav_grid: Res<NavGrid>,
+    flow_fields: Res<FlowFields>,
+    mut query: Query<(&mut Transform, &Minion, &TeamId, Option<&StatusEffects>), Without<Dead>>,
+) {
+    for (mut transform, minion, team, effects) in &mut query {
+        let position = transform.translation.truncate();
+        let destination = match minion.state {
+            MinionState::March => flow_fields
+                .get(*team)
+                .and_then(|flow_field| flow_field.waypoint(&nav_grid, position)),
+            MinionState::Attack { .. } if minion.kind.keeps_distance() => None,
+            _ => minion.destination,
+        };
+        let Some(destination) = destination else {
+            continue;
+        };
 
-            transform.translation.x +=
-                time.delta_seconds() * MINION_SCALE * normalized_target_position.x;
-            transform.translation.y +=
-                time.delta_seconds() * MINION_SCALE * normalized_target_position.y;
-        }
+        let direction = (destination - position).normalize_or_zero();
+        let speed = balance.minions.get(minion.kind).speed * speed_factor(effects);
+        transform.translation += (direction * speed * time.delta_seconds()).extend(0.);
     }
 }
 
-fn destroy_minions(
-    mut commands: Commands,
-    query: Query<(&Transform, &Health, Entity), With<Minion>>,
+// minions leaving the world die
+fn leave_world(
+    bounds: Res<WorldBounds>,
+    mut damage_events: EventWriter<DamageEvent>,
+    query: Query<(&Transform, Entity), (With<Minion>, Without<Dead>)>,
 ) {
-    let mut kill = |entity| {
-        commands.entity(entity).despawn_recursive();
-    };
-
-    for (transform, health, entity) in query.iter() {
-        // edge of the world
-        if transform.translation.x.abs() >= GAME_MAX_WIDTH / 2.
-            || transform.translation.y.abs() >= GAME_MAX_HEIGHT / 2.
+    for (transform, entity) in query.iter() {
+        if transform.translation.x.abs() >= bounds.size.x / 2.
+            || transform.translation.y.abs() >= bounds.size.y / 2.
         {
-            kill(entity);
-        }
-
-        // just not enough health
-        if health.is_dead() {
-            kill(entity);
+            damage_events.send(DamageEvent::lethal(entity));
         }
     }
 }
@@ -242,115 +386,350 @@ fn destroy_minions(
 fn destroy_after_timer(
     time: Res<Time>,
     mut commands: Commands,
-    mut query: Query<(&mut TimeDestroyable, Entity)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut query: Query<(&mut TimeDestroyable, Entity, Option<&Health>)>,
 ) {
-    for (mut time_destroyable, entity) in &mut query {
+    for (mut time_destroyable, entity, health) in &mut query {
         if time_destroyable.timer.tick(time.delta()).just_finished() {
-            commands.entity(entity).despawn_recursive();
+            // units die of old age, the rest just vanishes
+            if health.is_some() {
+                damage_events.send(DamageEvent::lethal(entity));
+            } else {
+                commands.entity(entity).despawn_recursive();
+            }
         }
     }
 }
 
-// FIXME: With explosion implem, it can be simplify
+// kamikazes explode on the first hostile thing they touch
+#[allow(clippy::too_many_arguments)]
 fn check_collisions_minions(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    audio_explosion: Res<AudioExplosion>,
+    audio_explosion: Option<Res<AudioExplosion>>,
+    balance: Res<Balance>,
+    teams: Res<Teams>,
     mut collision_events: EventReader<CollisionEvent>,
-    // queries
-    mut query_minions: Query<(&Transform, &Team, &mut Minion), With<Minion>>,
-    query_hit_entities: Query<&Team, Without<Minion>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut query_minions: Query<(&Transform, &TeamId, &mut Minion, Option<&StatusEffects>)>,
+    query_teams: Query<&TeamId>,
 ) {
     for collision_event in collision_events.read() {
-        match collision_event {
-            CollisionEvent::Started(e1, e2) => {
-                // between minions
-                if query_minions.contains(*e1) && query_minions.contains(*e2) {
-                    let [(transform_a, team_a, mut minion_a), (_, team_b, _)] =
-                        match query_minions.get_many_mut([*e1, *e2]) {
-                            Err(_) => continue,
-                            Ok(m) => m,
-                        };
-
-                    // if minion a already exploded
-                    if minion_a.had_exploded {
-                        continue;
-                    }
+        let CollisionEvent::Started(e1, e2) = collision_event else {
+            continue;
+        };
 
-                    // if they are from the same team, do nothing special
-                    if team_a.id == team_b.id {
+        for (entity, other) in [(*e1, *e2), (*e2, *e1)] {
+            let Ok((transform, team, mut minion, effects)) = query_minions.get_mut(entity) else {
+                continue;
+            };
+            if minion.kind != MinionKind::Kamikaze || minion.had_exploded {
+                continue;
+            }
+            // stunned minions do nothing, as in `attack`
+            if effects.is_some_and(StatusEffects::is_stunned) {
+                continue;
+            }
+
+            // allies and neutrals do not explode on each other
+            let Ok(other_team) = query_teams.get(other) else {
+                continue;
+            };
+            if !teams.is_hostile(*team, *other_team) {
+                continue;
+            }
+
+            spawn_explosion(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                audio_explosion.as_deref(),
+                transform.translation,
+                teams.get(*team),
+                balance.minions.kamikaze.damage,
+            );
+
+            minion.had_exploded = true;
+            // explosions spare allies, so the minion kills itself
+            damage_events.send(DamageEvent::lethal(entity));
+        }
+    }
+}
+
+// melees and tanks hit, ranged shoot and healers heal, once their cooldown is over
+#[allow(clippy::too_many_arguments)]
+fn attack(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time>,
+    balance: Res<Balance>,
+    teams: Res<Teams>,
+    grid: Res<SpatialGrid>,
+    collisions: Res<Collisions>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut heal_events: EventWriter<HealEvent>,
+    query_health: Query<&Health>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Minion,
+            &Transform,
+            &TeamId,
+            Option<&StatusEffects>,
+        ),
+        Without<Dead>,
+    >,
+) {
+    for (entity, mut minion, transform, team, effects) in &mut query {
+        if effects.is_some_and(StatusEffects::is_stunned) {
+            continue;
+        }
+        // slowed minions act slower too
+        let delta = time.delta().mul_f32(speed_factor(effects));
+        if !minion.cooldown.tick(delta).finished() {
+            continue;
+        }
+
+        let stats = balance.minions.get(minion.kind);
+        let position = transform.translation.truncate();
+        let target = match (minion.state, minion.destination) {
+            (
+                MinionState::Chase { target } | MinionState::Attack { target },
+                Some(target_position),
+            ) => Some((target, target_position)),
+            _ => None,
+        };
+        let in_range = |target_position: Vec2| {
+            position.distance_squared(target_position) <= stats.attack_radius * stats.attack_radius
+        };
+
+        let acted = match (minion.kind, target) {
+            (MinionKind::Melee | MinionKind::Tank, Some((target, target_position))) => {
+                // big targets (castles) are touched far from their center
+                let touching = collisions.contains(&EntityPair::new(entity, target));
+                let hit = touching || in_range(target_position);
+                if hit {
+                    damage_events.send(DamageEvent {
+                        target,
+                        source: Some(entity),
+                        team: Some(*team),
+                        amount: stats.damage,
+                        kind: DamageKind::Physical,
+                    });
+                }
+                hit
+            }
+            (MinionKind::Ranged, Some((_, target_position))) if in_range(target_position) => {
+                let direction = (target_position - position).normalize_or_zero();
+                commands.spawn(ProjectileBundle::new(
+                    &mut meshes,
+                    &mut materials,
+                    Some(entity),
+                    transform.translation,
+                    direction * stats.projectile_speed,
+                    // a bit farther than the target
+                    stats.attack_radius * 1.5 / stats.projectile_speed.max(1.),
+                    teams.get(*team),
+                    stats.damage,
+                ));
+                true
+            }
+            (MinionKind::Healer, _) => {
+                let mut healed = false;
+                for ally in grid.within(position, stats.attack_radius) {
+                    // not themselves
+                    if ally.entity == entity || !teams.is_ally(*team, ally.team) {
                         continue;
                     }
+                    if query_health
+                        .get(ally.entity)
+                        .is_ok_and(|health| health.value < health.max)
+                    {
+                        heal_events.send(HealEvent {
+                            target: ally.entity,
+                            source: Some(entity),
+                            amount: stats.heal,
+                        });
+                        healed = true;
+                    }
+                }
+                healed
+            }
+            // kamikazes explode on contact
+            _ => false,
+        };
 
-                    commands.spawn(ExplosionBundle::new(
-                        &mut meshes,
-                        &mut materials,
-                        &audio_explosion.0,
-                        transform_a.translation,
-                        team_a.clone(),
-                    ));
+        if acted {
+            minion.cooldown.reset();
+        }
+    }
+}
 
-                    minion_a.had_exploded = true;
+/// Shot by ranged minions, hurts the first non-allied unit it touches.
+#[derive(Component)]
+pub struct Projectile {
+    damage: f32,
+    /// pixels per second
+    velocity: Vec2,
+    /// the minion which shot it, if known
+    source: Option<Entity>,
+    had_hit: bool,
+}
 
-                    continue;
-                }
+impl Projectile {
+    pub fn new(damage: f32, velocity: Vec2, source: Option<Entity>) -> Self {
+        Self {
+            damage,
+            velocity,
+            source,
+            had_hit: false,
+        }
+    }
 
-                // minion vs others
-                let (minion_transform, minion_team, mut minion) = match query_minions.get_mut(*e1) {
-                    Err(_) => match query_minions.get_mut(*e2) {
-                        Err(_) => continue,
-                        Ok(value) => value,
-                    },
-                    Ok(value) => value,
-                };
+    pub fn damage(&self) -> f32 {
+        self.damage
+    }
 
-                let Ok(team) = query_hit_entities
-                    .get(*e1)
-                    .or_else(|_| query_hit_entities.get(*e2))
-                else {
-                    continue;
-                };
+    pub fn velocity(&self) -> Vec2 {
+        self.velocity
+    }
 
-                if minion.had_exploded {
-                    continue;
-                }
+    pub fn source(&self) -> Option<Entity> {
+        self.source
+    }
+}
 
-                // if they are from the same team, do nothing special
-                if minion_team.id == team.id {
-                    continue;
-                }
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    mesh: MaterialMesh2dBundle<ColorMaterial>,
+    projectile: Projectile,
+    match_entity: MatchEntity,
+    team: TeamId,
+    sensor: Sensor,
+    collider: Collider,
+    // without a body it is fixed, which only touches dynamic bodies by default
+    active_collision_types: ActiveCollisionTypes,
+    timer_destroyable: TimeDestroyable,
+}
 
-                commands.spawn(ExplosionBundle::new(
-                    &mut meshes,
-                    &mut materials,
-                    &audio_explosion.0,
-                    minion_transform.translation,
-                    minion_team.clone(),
-                ));
+impl ProjectileBundle {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        meshes: &mut ResMut<Assets<Mesh>>,
+        materials: &mut ResMut<Assets<ColorMaterial>>,
+        source: Option<Entity>,
+        mut translation: Vec3,
+        velocity: Vec2,
+        lifetime_secs: f32,
+        team: &Team,
+        damage: f32,
+    ) -> Self {
+        let radius = 2.;
+        translation.z = 5.0;
+        ProjectileBundle {
+            mesh: MaterialMesh2dBundle {
+                mesh: meshes.add(shape::Circle::new(radius).into()).into(),
+                material: materials.add(ColorMaterial::from(team.color)),
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            projectile: Projectile::new(damage, velocity, source),
+            match_entity: MatchEntity,
+            team: team.id,
+            sensor: Sensor,
+            collider: Collider::ball(radius),
+            active_collision_types: ActiveCollisionTypes::all(),
+            timer_destroyable: TimeDestroyable {
+                timer: Timer::from_seconds(lifetime_secs, TimerMode::Once),
+            },
+        }
+    }
+}
 
-                minion.had_exploded = true;
+fn move_projectiles(time: Res<Time>, mut query: Query<(&mut Transform, &Projectile)>) {
+    for (mut transform, projectile) in &mut query {
+        transform.translation += (projectile.velocity * time.delta_seconds()).extend(0.);
+    }
+}
+
+// projectiles hurt the first unit they touch, except allies, and stop at obstacles
+fn projectile_hits(
+    mut commands: Commands,
+    teams: Res<Teams>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut query_projectiles: Query<(&mut Projectile, &TeamId)>,
+    query_hit_entities: Query<Option<&TeamId>, (With<Health>, Without<Dead>)>,
+    query_obstacles: Query<(), With<Obstacle>>,
+) {
+    for collision_event in collision_events.read() {
+        let CollisionEvent::Started(e1, e2) = collision_event else {
+            continue;
+        };
+
+        for (entity, other) in [(*e1, *e2), (*e2, *e1)] {
+            let Ok((mut projectile, projectile_team)) = query_projectiles.get_mut(entity) else {
+                continue;
+            };
+            if projectile.had_hit {
+                continue;
             }
-            CollisionEvent::Stopped(_, _) => {}
+            if query_obstacles.contains(other) {
+                projectile.had_hit = true;
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+            let Ok(team) = query_hit_entities.get(other) else {
+                continue;
+            };
+            if team.is_some_and(|team| teams.is_ally(*projectile_team, *team)) {
+                continue;
+            }
+
+            damage_events.send(DamageEvent {
+                target: other,
+                source: projectile.source,
+                team: Some(*projectile_team),
+                amount: projectile.damage,
+                kind: DamageKind::Physical,
+            });
+            projectile.had_hit = true;
+            commands.entity(entity).despawn_recursive();
         }
     }
 }
 
-fn decay_life(time: Res<Time>, mut query_minions: Query<&mut Health, With<Minion>>) {
-    for mut health in &mut query_minions {
-        health.hit(DECAY_VALUE_PER_SEC * time.delta_seconds());
+fn decay_life(
+    time: Res<Time>,
+    balance: Res<Balance>,
+    mut damage_events: EventWriter<DamageEvent>,
+    query_minions: Query<(Entity, &Minion)>,
+) {
+    for (entity, minion) in &query_minions {
+        damage_events.send(DamageEvent {
+            target: entity,
+            source: None,
+            team: None,
+            amount: balance.minions.get(minion.kind).decay_per_sec * time.delta_seconds(),
+            kind: DamageKind::Decay,
+        });
     }
 }
 
 fn explosion_damage(
+    teams: Res<Teams>,
     mut collision_events: EventReader<CollisionEvent>,
-    mut query_hit_entities: Query<&mut Health, Without<Explosion>>,
-    query_explosions: Query<&Explosion>,
+    mut damage_events: EventWriter<DamageEvent>,
+    query_hit_entities: Query<(Entity, Option<&TeamId>), (With<Health>, Without<Explosion>)>,
+    query_explosions: Query<(Entity, &Explosion, &TeamId)>,
 ) {
     for collision_event in collision_events.read() {
         match collision_event {
             CollisionEvent::Started(e1, e2) => {
-                let explosion = match query_explosions.get(*e1) {
+                let (explosion_entity, explosion, explosion_team) = match query_explosions.get(*e1)
+                {
                     Err(_) => match query_explosions.get(*e2) {
                         Err(_) => continue,
                         Ok(value) => value,
@@ -358,15 +737,26 @@ fn explosion_damage(
                     Ok(value) => value,
                 };
 
-                let mut health = match query_hit_entities.get_mut(*e1) {
-                    Err(_) => match query_hit_entities.get_mut(*e2) {
+                let (target, team) = match query_hit_entities.get(*e1) {
+                    Err(_) => match query_hit_entities.get(*e2) {
                         Err(_) => continue,
                         Ok(value) => value,
                     },
                     Ok(value) => value,
                 };
 
-                health.hit(explosion.damage);
+                // allies are not hurt by explosions
+                if team.is_some_and(|team| teams.is_ally(*explosion_team, *team)) {
+                    continue;
+                }
+
+                damage_events.send(DamageEvent {
+                    target,
+                    source: Some(explosion_entity),
+                    team: Some(*explosion_team),
+                    amount: explosion.damage,
+                    kind: DamageKind::Explosive,
+                });
             }
             CollisionEvent::Stopped(_, _) => {}
         }
@@ -0,0 +1,625 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    castles::Castle,
+    common::{GameSet, WorldBounds},
+    health::{Dead, Died},
+    teams::{TeamId, Teams},
+};
+
+/// Side of a navigation cell.
+pub const NAV_CELL_SIZE: f32 = 20.;
+// obstacles are inflated by this, about a minion radius
+const NAV_MARGIN: f32 = 6.;
+
+// A* costs, diagonals are about sqrt(2)
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// Blocks the navigation grid with its collider: map obstacles, castles, racks.
+#[derive(Component, Clone, Copy, Default)]
+pub struct NavObstacle;
+
+/// Walkable cells of the world, rebuilt in `GameSet::Collisions` when obstacles are added or die.
+#[derive(Resource)]
+pub struct NavGrid {
+    width: u32,
+    height: u32,
+    // world position of the bottom left corner
+    origin: Vec2,
+    // indexed by `y * width + x`
+    blocked: Vec<bool>,
+}
+
+impl Default for NavGrid {
+    fn default() -> Self {
+        Self::new(WorldBounds::default().size)
+    }
+}
+
+impl NavGrid {
+    pub fn new(size: Vec2) -> Self {
+        let width = (size.x / NAV_CELL_SIZE).ceil().max(1.) as u32;
+        let height = (size.y / NAV_CELL_SIZE).ceil().max(1.) as u32;
+        Self {
+            width,
+            height,
+            origin: -size / 2.,
+            blocked: vec![false; (width * height) as usize],
+        }
+    }
+
+    /// Blocks every cell overlapped by this rectangle.
+    pub fn block(&mut self, min: Vec2, max: Vec2) {
+        let (min, max) = (self.cell(min), self.cell(max));
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let index = self.index(UVec2::new(x, y));
+                self.blocked[index] = true;
+            }
+        }
+    }
+
+    /// Cell containing `position`, positions out of the world are clamped to it.
+    pub fn cell(&self, position: Vec2) -> UVec2 {
+        let cell = ((position - self.origin) / NAV_CELL_SIZE).floor();
+        UVec2::new(
+            cell.x.clamp(0., (self.width - 1) as f32) as u32,
+            cell.y.clamp(0., (self.height - 1) as f32) as u32,
+        )
+    }
+
+    /// World position of the center of `cell`.
+    pub fn center(&self, cell: UVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + 0.5) * NAV_CELL_SIZE
+    }
+
+    pub fn is_walkable(&self, cell: UVec2) -> bool {
+        !self.blocked[self.index(cell)]
+    }
+
+    fn index(&self, cell: UVec2) -> usize {
+        (cell.y * self.width + cell.x) as usize
+    }
+
+    fn cell_at(&self, index: usize) -> UVec2 {
+        UVec2::new(index as u32 % self.width, index as u32 / self.width)
+    }
+
+    // the 8 cells around, diagonals only when both sides are walkable (no corner cutting)
+    fn neighbors(&self, index: usize, walkable: impl Fn(usize) -> bool) -> Vec<(usize, u32)> {
+        let cell = self.cell_at(index).as_ivec2();
+        let at = |dx: i32, dy: i32| {
+            let next = cell + IVec2::new(dx, dy);
+            (next.x >= 0
+                && next.y >= 0
+                && next.x < self.width as i32
+                && next.y < self.height as i32)
+                .then(|| self.index(next.as_uvec2()))
+                .filter(|next| walkable(*next))
+        };
+
+        let mut neighbors = vec![];
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            if let Some(next) = at(dx, dy) {
+                neighbors.push((next, STRAIGHT_COST));
+            }
+        }
+        for (dx, dy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            if at(dx, 0).is_some() && at(0, dy).is_some() {
+                if let Some(next) = at(dx, dy) {
+                    neighbors.push((next, DIAGONAL_COST));
+                }
+            }
+        }
+        neighbors
+    }
+
+    // blocked cells connected to `index` (itself included): a goal in there is reached from
+    // any of them, a unit standing in there can walk through them to get out
+    fn blocked_region(&self, index: usize) -> HashSet<usize> {
+        let mut region = HashSet::default();
+        region.insert(index);
+        if !self.blocked[index] {
+            return region;
+        }
+
+        let mut open = vec![index];
+        while let Some(index) = open.pop() {
+            for (next, _) in self.neighbors(index, |next| self.blocked[next]) {
+                if region.insert(next) {
+                    open.push(next);
+                }
+            }
+        }
+        region
+    }
+
+    /// Cells from `from` to `to` (both included) with A*, `None` when `to` cannot be reached.
+    ///
+    /// `to` can be inside an obstacle (a castle to attack), the path then ends at its border.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<UVec2>> {
+        self.find_path_indexes(self.index(self.cell(from)), self.index(self.cell(to)))
+            .map(|path| path.into_iter().map(|index| self.cell_at(index)).collect())
+    }
+
+    fn find_path_indexes(&self, start: usize, goal: usize) -> Option<Vec<usize>> {
+        let goal_region = self.blocked_region(goal);
+        let start_region = self.blocked_region(start);
+        let goal_cell = self.cell_at(goal);
+        let heuristic = |index: usize| {
+            let delta = (self.cell_at(index).as_ivec2() - goal_cell.as_ivec2()).abs();
+            let (min, max) = (delta.min_element() as u32, delta.max_element() as u32);
+            STRAIGHT_COST * max + (DIAGONAL_COST - STRAIGHT_COST) * min
+        };
+
+        let mut costs = vec![u32::MAX; self.blocked.len()];
+        let mut came_from = vec![usize::MAX; self.blocked.len()];
+        // ties go to the lowest index, paths are the same on every machine
+        let mut open = BinaryHeap::new();
+        costs[start] = 0;
+        open.push(Reverse((heuristic(start), start)));
+
+        while let Some(Reverse((_, index))) = open.pop() {
+            if goal_region.contains(&index) {
+                let mut path = vec![index];
+                let mut current = index;
+                while current != start {
+                    current = came_from[current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            // units may stand inside an obstacle border, they can always leave it
+            let walkable = |next: usize| {
+                !self.blocked[next] || goal_region.contains(&next) || start_region.contains(&next)
+            };
+            for (next, step) in self.neighbors(index, walkable) {
+                let cost = costs[index] + step;
+                if cost < costs[next] {
+                    costs[next] = cost;
+                    came_from[next] = index;
+                    open.push(Reverse((cost + heuristic(next), next)));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+// paths already found toward a goal cell
+#[derive(Default)]
+struct GoalPaths {
+    paths: Vec<Vec<usize>>,
+    // cell index to its path and step in it, the first path found through a cell wins
+    steps: HashMap<usize, (usize, usize)>,
+    // no path from these
+    unreachable: HashSet<usize>,
+}
+
+/// Paths by goal, cleared with the `NavGrid`: units sharing a goal reuse the path
+/// of any unit which went through their cell before.
+#[derive(Resource, Default)]
+pub struct PathCache {
+    goals: HashMap<usize, GoalPaths>,
+}
+
+impl PathCache {
+    pub fn clear(&mut self) {
+        self.goals.clear();
+    }
+
+    /// Where to walk to from `from` to eventually reach `to`, `None` when it cannot be reached.
+    pub fn next_waypoint(&mut self, grid: &NavGrid, from: Vec2, to: Vec2) -> Option<Vec2> {
+        let start = grid.index(grid.cell(from));
+        let goal = grid.index(grid.cell(to));
+        let goal_paths = self.goals.entry(goal).or_default();
+
+        let (path, step) = match goal_paths.steps.get(&start) {
+            Some(found) => *found,
+            None => {
+                if goal_paths.unreachable.contains(&start) {
+                    return None;
+                }
+                let Some(cells) = grid.find_path_indexes(start, goal) else {
+                    goal_paths.unreachable.insert(start);
+                    return None;
+                };
+
+                let path = goal_paths.paths.len();
+                for (step, cell) in cells.iter().enumerate() {
+                    goal_paths.steps.entry(*cell).or_insert((path, step));
+                }
+                goal_paths.paths.push(cells);
+                goal_paths.steps[&start]
+            }
+        };
+
+        let cells = &goal_paths.paths[path];
+        // the last cell is the goal, or the border of the obstacle around it
+        if step + 2 >= cells.len() {
+            return Some(to);
+        }
+        Some(grid.center(grid.cell_at(cells[step + 1])))
+    }
+}
+
+/// Where to walk from every cell to reach the closest hostile castle, for one team.
+#[derive(Clone)]
+pub struct FlowField {
+    // cell index to the next cell toward a castle, `usize::MAX` when none can be reached,
+    // castle cells lead to themselves
+    next: Vec<usize>,
+}
+
+impl FlowField {
+    /// Multi-source Dijkstra from every castle border, walking back the grid.
+    pub fn new(grid: &NavGrid, castles: impl IntoIterator<Item = Vec2>) -> Self {
+        let mut next = vec![usize::MAX; grid.blocked.len()];
+        let mut costs = vec![u32::MAX; grid.blocked.len()];
+        let mut open = BinaryHeap::new();
+        for castle in castles {
+            for index in grid.blocked_region(grid.index(grid.cell(castle))) {
+                next[index] = index;
+                costs[index] = 0;
+                open.push(Reverse((0, index)));
+            }
+        }
+
+        while let Some(Reverse((cost, index))) = open.pop() {
+            if cost > costs[index] {
+                continue;
+            }
+            for (previous, step) in grid.neighbors(index, |previous| !grid.blocked[previous]) {
+                if cost + step < costs[previous] {
+                    costs[previous] = cost + step;
+                    next[previous] = index;
+                    open.push(Reverse((cost + step, previous)));
+                }
+            }
+        }
+
+        Self { next }
+    }
+
+    /// Where to walk to from `position`, `None` once at a castle or when none can be reached.
+    pub fn waypoint(&self, grid: &NavGrid, position: Vec2) -> Option<Vec2> {
+        let index = grid.index(grid.cell(position));
+        let next = match self.next[index] {
+            next if next == index => return None,
+            usize::MAX => self.way_out(grid, index)?,
+            next => next,
+        };
+        Some(grid.center(grid.cell_at(next)))
+    }
+
+    // spawned or pushed into an obstacle border: the closest cell having a way, searched
+    // through the obstacle only (walkable cells without a way lead nowhere)
+    fn way_out(&self, grid: &NavGrid, index: usize) -> Option<usize> {
+        let mut visited = HashSet::default();
+        visited.insert(index);
+        let mut open = VecDeque::from([index]);
+        while let Some(current) = open.pop_front() {
+            if current != index && !grid.blocked[current] {
+                continue;
+            }
+            for (neighbor, _) in grid.neighbors(current, |_| true) {
+                if self.next[neighbor] != usize::MAX {
+                    return Some(neighbor);
+                }
+                if visited.insert(neighbor) {
+                    open.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// A `FlowField` by team, recomputed with the `NavGrid` or when teams change.
+///
+/// Every minion of a team marching toward castles samples the same field.
+#[derive(Resource, Default)]
+pub struct FlowFields {
+    fields: HashMap<TeamId, FlowField>,
+}
+
+impl FlowFields {
+    pub fn get(&self, team: TeamId) -> Option<&FlowField> {
+        self.fields.get(&team)
+    }
+}
+
+// an obstacle died since the last rebuild
+#[derive(Resource, Default)]
+struct NavGridDirty(bool);
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavGrid>()
+            .init_resource::<PathCache>()
+            .init_resource::<FlowFields>()
+            .init_resource::<NavGridDirty>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    (rebuild, rebuild_flow_fields)
+                        .chain()
+                        .in_set(GameSet::Collisions),
+                    mark_dead_obstacles.in_set(GameSet::Cleanup),
+                ),
+            );
+    }
+}
+
+fn rebuild(
+    bounds: Res<WorldBounds>,
+    mut grid: ResMut<NavGrid>,
+    mut cache: ResMut<PathCache>,
+    mut dirty: ResMut<NavGridDirty>,
+    query_added: Query<(), Added<NavObstacle>>,
+    query: Query<(&Transform, &Collider), (With<NavObstacle>, Without<Dead>)>,
+) {
+    // a new match changes the bounds
+    if !bounds.is_changed() && !dirty.0 && query_added.is_empty() {
+        return;
+    }
+    dirty.0 = false;
+
+    *grid = NavGrid::new(bounds.size);
+    for (transform, collider) in &query {
+        let half_size = match (collider.as_cuboid(), collider.as_ball()) {
+            (Some(cuboid), _) => cuboid.half_extents(),
+            (_, Some(ball)) => Vec2::splat(ball.radius()),
+            _ => continue,
+        };
+        // bounding box of the rotated collider
+        let corners = [
+            half_size,
+            Vec2::new(-half_size.x, half_size.y),
+            -half_size,
+            Vec2::new(half_size.x, -half_size.y),
+        ];
+        let extent = corners
+            .iter()
+            .map(|corner| (transform.rotation * corner.extend(0.)).truncate().abs())
+            .fold(Vec2::ZERO, Vec2::max)
+            + NAV_MARGIN;
+        let center = transform.translation.truncate();
+        grid.block(center - extent, center + extent);
+    }
+    cache.clear();
+    debug!("[navigation] rebuilt");
+}
+
+// `Died` is read in the tick it is sent, removals would be lost between frames without a tick
+fn mark_dead_obstacles(
+    mut dirty: ResMut<NavGridDirty>,
+    mut died_events: EventReader<Died>,
+    query: Query<(), With<NavObstacle>>,
+) {
+    let died = died_events
+        .read()
+        .filter(|died| query.contains(died.entity))
+        .count();
+    if died > 0 {
+        dirty.0 = true;
+    }
+}
+
+fn rebuild_flow_fields(
+    grid: Res<NavGrid>,
+    teams: Res<Teams>,
+    mut flow_fields: ResMut<FlowFields>,
+    query_castles: Query<(&Transform, &TeamId), (With<Castle>, Without<Dead>)>,
+) {
+    if !grid.is_changed() && !teams.is_changed() {
+        return;
+    }
+
+    flow_fields.fields.clear();
+    for team in teams.alive() {
+        let castles = query_castles
+            .iter()
+            .filter(|(_, castle_team)| teams.is_hostile(team.id, **castle_team))
+            .map(|(transform, _)| transform.translation.truncate());
+        flow_fields
+            .fields
+            .insert(team.id, FlowField::new(&grid, castles));
+    }
+    debug!("[navigation] flow fields rebuilt");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10 by 10 cells, from -100 to 100
+    fn grid() -> NavGrid {
+        NavGrid::new(Vec2::splat(10. * NAV_CELL_SIZE))
+    }
+
+    fn assert_connected(path: &[UVec2]) {
+        for step in path.windows(2) {
+            let delta = (step[0].as_ivec2() - step[1].as_ivec2()).abs();
+            assert_eq!(delta.max_element(), 1, "{:?} is not a step", step);
+        }
+    }
+
+    #[test]
+    fn path_goes_around_a_wall() {
+        let mut grid = grid();
+        // cells x = 5, y = 0 to 8, the last row is open
+        grid.block(Vec2::new(0., -100.), Vec2::new(1., 60.));
+
+        let path = grid
+            .find_path(Vec2::new(-90., -90.), Vec2::new(90., -90.))
+            .expect("no path around the wall");
+        assert_eq!(path.first(), Some(&UVec2::new(0, 0)));
+        assert_eq!(path.last(), Some(&UVec2::new(9, 0)));
+        assert_connected(&path);
+        assert!(path.iter().all(|cell| grid.is_walkable(*cell)));
+        assert!(path.iter().any(|cell| cell.y == 9));
+    }
+
+    #[test]
+    fn walled_goal_is_unreachable() {
+        let mut grid = grid();
+        grid.block(Vec2::new(0., -100.), Vec2::new(1., 100.));
+        let (from, to) = (Vec2::new(-90., -90.), Vec2::new(90., -90.));
+
+        assert!(grid.find_path(from, to).is_none());
+
+        let mut cache = PathCache::default();
+        assert!(cache.next_waypoint(&grid, from, to).is_none());
+        // remembered, not searched again
+        let goal = grid.index(grid.cell(to));
+        assert!(cache.goals[&goal]
+            .unreachable
+            .contains(&grid.index(grid.cell(from))));
+        assert!(cache.next_waypoint(&grid, from, to).is_none());
+    }
+
+    #[test]
+    fn path_ends_at_the_border_of_a_goal_inside_an_obstacle() {
+        let mut grid = grid();
+        // cells 4 and 5 on both axes, like a castle
+        grid.block(Vec2::splat(-19.), Vec2::splat(19.));
+
+        let path = grid
+            .find_path(Vec2::new(-90., -90.), Vec2::ZERO)
+            .expect("no path to the castle");
+        assert_connected(&path);
+        let (last, walked) = path.split_last().unwrap();
+        assert!(!grid.is_walkable(*last));
+        assert!(last.x >= 4 && last.x <= 5 && last.y >= 4 && last.y <= 5);
+        assert!(walked.iter().all(|cell| grid.is_walkable(*cell)));
+    }
+
+    #[test]
+    fn path_leaves_an_obstacle_border() {
+        let mut grid = grid();
+        // cells 3 to 6, the start and all its neighbors are blocked
+        grid.block(Vec2::splat(-39.), Vec2::splat(39.));
+
+        let path = grid
+            .find_path(Vec2::ZERO, Vec2::new(90., 90.))
+            .expect("stuck in the obstacle");
+        assert_connected(&path);
+        assert_eq!(path.first(), Some(&UVec2::new(5, 5)));
+        assert_eq!(path.last(), Some(&UVec2::new(9, 9)));
+        // once out, it does not go back in
+        let out = path
+            .iter()
+            .position(|cell| grid.is_walkable(*cell))
+            .unwrap();
+        assert!(path[out..].iter().all(|cell| grid.is_walkable(*cell)));
+    }
+
+    #[test]
+    fn cached_paths_are_reused_along_the_way() {
+        let mut grid = grid();
+        grid.block(Vec2::new(0., -100.), Vec2::new(1., 60.));
+        let (from, to) = (Vec2::new(-90., -90.), Vec2::new(90., -90.));
+        let goal = grid.index(grid.cell(to));
+
+        let mut cache = PathCache::default();
+        let waypoint = cache
+            .next_waypoint(&grid, from, to)
+            .expect("no path around the wall");
+        assert_eq!(cache.goals[&goal].paths.len(), 1);
+
+        // walking the path, or starting anywhere on it, never searches again
+        let mut position = waypoint;
+        for _ in 0..30 {
+            if grid.cell(position) == grid.cell(to) {
+                break;
+            }
+            position = cache
+                .next_waypoint(&grid, position, to)
+                .expect("lost the path");
+        }
+        assert_eq!(grid.cell(position), grid.cell(to));
+        assert_eq!(cache.goals[&goal].paths.len(), 1);
+
+        // elsewhere, a new path is searched
+        cache.next_waypoint(&grid, Vec2::new(-90., 90.), to);
+        assert_eq!(cache.goals[&goal].paths.len(), 2);
+
+        cache.clear();
+        assert!(cache.goals.is_empty());
+    }
+
+    // the cell reached following the field, `None` when it never stops
+    fn follow(grid: &NavGrid, field: &FlowField, mut position: Vec2) -> Option<UVec2> {
+        for _ in 0..50 {
+            match field.waypoint(grid, position) {
+                Some(waypoint) => position = waypoint,
+                None => return Some(grid.cell(position)),
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn flow_field_leads_to_the_closest_castle() {
+        let grid = grid();
+        let field = FlowField::new(&grid, [Vec2::new(-90., -90.), Vec2::new(90., 90.)]);
+
+        assert_eq!(
+            follow(&grid, &field, Vec2::new(70., 30.)),
+            Some(UVec2::new(9, 9))
+        );
+        assert_eq!(
+            follow(&grid, &field, Vec2::new(-30., -70.)),
+            Some(UVec2::new(0, 0))
+        );
+    }
+
+    #[test]
+    fn flow_field_goes_around_obstacles() {
+        let mut grid = grid();
+        grid.block(Vec2::new(0., -100.), Vec2::new(1., 60.));
+        let field = FlowField::new(&grid, [Vec2::new(90., -90.)]);
+
+        assert_eq!(
+            follow(&grid, &field, Vec2::new(-90., -90.)),
+            Some(UVec2::new(9, 0))
+        );
+    }
+
+    #[test]
+    fn minions_inside_an_obstacle_margin_get_out() {
+        let mut grid = grid();
+        // cells 2 to 7, a minion at the center is two cells deep
+        grid.block(Vec2::splat(-59.), Vec2::splat(59.));
+        let field = FlowField::new(&grid, [Vec2::new(90., 90.)]);
+
+        assert!(field.waypoint(&grid, Vec2::ZERO).is_some());
+        assert_eq!(follow(&grid, &field, Vec2::ZERO), Some(UVec2::new(9, 9)));
+    }
+
+    #[test]
+    fn flow_field_without_castle_leads_nowhere() {
+        let mut grid = grid();
+        grid.block(Vec2::splat(-59.), Vec2::splat(59.));
+        let field = FlowField::new(&grid, []);
+
+        assert!(field.waypoint(&grid, Vec2::ZERO).is_none());
+        assert!(field.waypoint(&grid, Vec2::new(90., 90.)).is_none());
+    }
+}
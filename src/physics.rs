@@ -1,7 +1,17 @@
 use std::{collections::HashMap, hash::Hash, hash::Hasher};
 
-use bevy::prelude::*;
-use bevy_rapier2d::prelude::RapierContext;
+use bevy::{ecs::schedule::ExecutorKind, prelude::*};
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    common::{GameSet, Tick},
+    state::GameState,
+};
+
+/// Fixed updates per second, every gameplay system and the physics step run at this rate.
+pub const TICK_RATE: f64 = 60.;
+
+const PIXELS_PER_METER: f32 = 100.;
 
 #[derive(Event)]
 pub enum CollisionEvent {
@@ -75,12 +85,75 @@ pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Collisions::new())
-            .add_event::<CollisionEvent>()
-            .add_systems(PostUpdate, check_collisions);
+        app.add_plugins((
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(PIXELS_PER_METER)
+                .in_fixed_schedule(),
+            // RapierDebugRenderPlugin::default(),
+        ))
+        .insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+        .insert_resource(RapierConfiguration {
+            // removes gravity
+            gravity: Vec2::new(0.0, 0.0),
+            // one physics step per fixed update
+            timestep_mode: TimestepMode::Fixed {
+                dt: (1. / TICK_RATE) as f32,
+                substeps: 1,
+            },
+            ..default()
+        })
+        .insert_resource(Collisions::new())
+        .insert_resource(Tick::default())
+        .add_event::<CollisionEvent>()
+        // systems running in parallel can finish in any order,
+        // running them one after the other keeps the simulation deterministic
+        // (match setup too: it forks the global RNG)
+        .edit_schedule(OnExit(GameState::MainMenu), |schedule| {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        })
+        .edit_schedule(FixedUpdate, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        })
+        .configure_sets(
+            FixedUpdate,
+            (
+                PhysicsSet::Writeback,
+                GameSet::Collisions,
+                GameSet::Logic,
+                GameSet::Damage,
+                GameSet::Cleanup,
+            )
+                .chain(),
+        )
+        // the world is frozen outside of a running match (menus, pause, game over)
+        .configure_sets(
+            FixedUpdate,
+            (
+                PhysicsSet::SyncBackend,
+                PhysicsSet::StepSimulation,
+                PhysicsSet::Writeback,
+                GameSet::Collisions,
+                GameSet::Logic,
+                GameSet::Damage,
+                GameSet::Cleanup,
+            )
+                .run_if(in_state(GameState::InGame)),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                advance_tick
+                    .before(PhysicsSet::SyncBackend)
+                    .run_if(in_state(GameState::InGame)),
+                check_collisions.in_set(GameSet::Collisions),
+            ),
+        );
     }
 }
 
+fn advance_tick(mut tick: ResMut<Tick>) {
+    tick.0 += 1;
+}
+
 fn check_collisions(
     mut collisions: ResMut<Collisions>,
     rapier_context: Res<RapierContext>,
@@ -1,8 +1,15 @@
+use crate::balance::Balance;
+use crate::castles::Castle;
 use crate::common::*;
-use crate::health::Health;
+use crate::effects::{speed_factor, StatusEffects};
+use crate::health::{DamageEvent, DamageKind, Damaged, Dead, DeathDelay, Died, Health};
+use crate::maps::{Map, MapSet};
 use crate::physics::CollisionEvent;
-use crate::racks::{RackBundle, RACK_GOLD_VALUE};
-use crate::teams::{Team, Teams};
+use crate::racks::RackBundle;
+use crate::state::{GameState, MatchEntity};
+use crate::stats::TeamStats;
+use crate::teams::{TeamId, Teams};
+use crate::victory::Defeated;
 use bevy::sprite::MaterialMesh2dBundle;
 use bevy::window::PrimaryWindow;
 use bevy::{
@@ -13,15 +20,23 @@ use bevy::{
 };
 use bevy_cameraman::{CameraBundle, Cameraman, Target};
 use bevy_rapier2d::prelude::*;
+use bevy_turborand::prelude::*;
+use serde::{Deserialize, Serialize};
 
 const DEFAULT_HAND_COLOR: Color = Color::rgb(0.8, 0.25, 0.24);
-const JOYSTICK_SCALE: f32 = 200.;
+// from the team castle, toward the middle of the world
+const RESPAWN_DISTANCE: f32 = 100.;
 
+/// Inputs are applied in this set (`FixedUpdate`), anything changing them for a tick runs before.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApplyInputsSet;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Cooldowns {
     pub sword: Timer,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub gold: f32,
     pub cooldowns: Cooldowns,
@@ -30,6 +45,18 @@ pub struct Player {
 #[derive(Component)]
 pub struct LocalPlayer;
 
+/// What the player wants to do.
+/// Devices fill it every frame, it is applied (and its actions consumed) on the next tick.
+#[derive(Component, Default, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PlayerInput {
+    /// each axis is between -1 and 1
+    pub movement: Vec2,
+    /// angle around the z axis
+    pub rotation: Option<f32>,
+    pub sword: bool,
+    pub rack: bool,
+}
+
 #[derive(Component)]
 struct Hand;
 
@@ -72,48 +99,86 @@ impl SwordBundle {
     }
 }
 
+/// The player entity and what it does with its inputs, whoever fills them (devices or a replay).
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        // the player joins a team of the map, once spawned
+        app.add_systems(OnExit(GameState::MainMenu), spawn_player.after(MapSet))
+            .add_systems(
+                FixedUpdate,
+                (
+                    respawn,
+                    update_cooldowns,
+                    apply_inputs.in_set(ApplyInputsSet),
+                    update_sword,
+                    check_collisions_sword,
+                )
+                    .chain()
+                    .in_set(GameSet::Logic),
+            )
+            .add_systems(FixedUpdate, (die, reward_kills).in_set(GameSet::Cleanup));
+    }
+}
+
+/// Devices, camera and UI of the local player, disabled when headless.
 pub struct LocalPlayerPlugin;
 
 impl Plugin for LocalPlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (setup, setup_ui)).add_systems(
-            Update,
-            (
-                // movements
-                update_axes,
-                keyboard_movements,
-                mouse_movements,
-                // actions
-                update_button_values,
-                mouse_actions,
-                keyboard_actions,
-                // others
-                check_collisions_sword,
-                update_ui,
-                update_sword,
-                update_cooldowns,
-            ),
-        );
+        app.add_systems(Startup, setup_ui)
+            .add_systems(
+                OnExit(GameState::MainMenu),
+                setup_camera.after(spawn_player),
+            )
+            .add_systems(
+                Update,
+                (
+                    reset_movements,
+                    (
+                        // movements
+                        update_axes,
+                        keyboard_movements,
+                        mouse_movements,
+                        // actions
+                        update_button_values,
+                        mouse_actions,
+                        keyboard_actions,
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(Update, (update_ui, update_hand));
     }
 }
 
-fn setup(
+fn spawn_player(
     mut commands: Commands,
+    map: Res<Map>,
     teams: Res<Teams>,
+    balance: Res<Balance>,
+    mut rand: ResMut<GlobalRng>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    let mut sword_cooldown = Timer::from_seconds(0.3, TimerMode::Once);
+    let mut sword_cooldown =
+        Timer::from_seconds(balance.player.sword_cooldown_secs, TimerMode::Once);
     sword_cooldown.set_elapsed(sword_cooldown.duration());
 
-    let team = teams.get_expect("a".into());
+    let Some((map_team, position)) = map.player() else {
+        info!("[player] no player in this map, spectating");
+        return;
+    };
+    let team = teams.get(teams.find(&map_team.id).expect("player team not found"));
 
-    let entity = commands
+    commands
         .spawn((
             MaterialMesh2dBundle {
                 mesh: meshes.add(shape::Circle::new(30.).into()).into(),
                 material: materials.add(ColorMaterial::from(team.color)),
-                transform: Transform::from_translation(Vec3::new(-150., 0., 0.)),
+                transform: Transform::from_translation(position.extend(0.)),
                 ..default()
             },
             // TODO:: add sprite sheet later
@@ -130,18 +195,23 @@ fn setup(
             // RigidBody::Dynamic,
             Collider::ball(28.),
             LocalPlayer {},
+            PlayerInput::default(),
             Player {
-                gold: 20.,
+                gold: balance.player.gold,
                 cooldowns: Cooldowns {
                     sword: sword_cooldown,
                 },
             },
-            Health::new(100.)
+            Health::new(balance.player.health)
                 .with_health_bar_position(Vec3::new(0.0, 40.0, 0.1))
                 .with_health_bar_size(Vec2::new(50.0, 5.0)),
+            balance.player.resistances,
+            balance.player.regeneration,
             Name("local_player".to_string()),
             Target,
-            team,
+            team.id,
+            RngComponent::from(&mut rand),
+            MatchEntity,
         ))
         .with_children(|parent| {
             parent.spawn((
@@ -156,12 +226,22 @@ fn setup(
                 },
                 Hand {},
             ));
-        })
-        .id();
+        });
+}
 
-    commands.spawn(CameraBundle::new(
-        Cameraman::new(entity, Vec2::new(50.0, 20.0), Vec3::ONE * 0.8),
-        Camera2dBundle::default(),
+// follows the local player, or looks at the whole map without one
+fn setup_camera(mut commands: Commands, query_local_player: Query<Entity, With<LocalPlayer>>) {
+    let Ok(entity) = query_local_player.get_single() else {
+        commands.spawn((Camera2dBundle::default(), MatchEntity));
+        return;
+    };
+
+    commands.spawn((
+        CameraBundle::new(
+            Cameraman::new(entity, Vec2::new(50.0, 20.0), Vec3::ONE * 0.8),
+            Camera2dBundle::default(),
+        ),
+        MatchEntity,
     ));
 }
 
@@ -187,11 +267,16 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 }
 
+fn reset_movements(mut query: Query<&mut PlayerInput, With<LocalPlayer>>) {
+    for mut input in &mut query {
+        input.movement = Vec2::ZERO;
+    }
+}
+
 fn update_axes(
-    time: Res<Time>,
     gamepads: Res<Gamepads>,
     axes: Res<Axis<GamepadAxis>>,
-    mut query: Query<&mut Transform, (With<LocalPlayer>, With<Children>)>,
+    mut query: Query<&mut PlayerInput, With<LocalPlayer>>,
 ) {
     // TODO: Affect one gamepad to local player
     for gamepad in gamepads.iter() {
@@ -201,8 +286,8 @@ fn update_axes(
         let mut moved = false;
         if left_stick_x.abs() > 0.1 {
             moved = true;
-            for mut transform in &mut query {
-                transform.translation.x += left_stick_x * JOYSTICK_SCALE * time.delta_seconds();
+            for mut input in &mut query {
+                input.movement.x += left_stick_x;
             }
         }
 
@@ -211,58 +296,33 @@ fn update_axes(
             .unwrap();
         if left_stick_y.abs() > 0.1 {
             moved = true;
-            for mut transform in &mut query {
-                transform.translation.y += left_stick_y * JOYSTICK_SCALE * time.delta_seconds();
+            for mut input in &mut query {
+                input.movement.y += left_stick_y;
             }
         }
 
         if moved {
-            for mut transform in &mut query {
-                transform.rotation = Quat::from_axis_angle(
-                    Vec3::new(0., 0., 1.),
-                    (-left_stick_x).atan2(left_stick_y),
-                );
+            for mut input in &mut query {
+                input.rotation = Some((-left_stick_x).atan2(left_stick_y));
             }
         }
     }
 }
 
 fn update_button_values(
-    mut commands: Commands,
     mut events: EventReader<GamepadButtonChangedEvent>,
-    mut query_local_player: Query<
-        (&mut Player, &Transform, &Team, Entity, &Children),
-        With<LocalPlayer>,
-    >,
-    mut query: Query<&mut Sprite, With<Hand>>,
+    mut query_local_player: Query<&mut PlayerInput, With<LocalPlayer>>,
 ) {
     for button_event in events.read() {
-        let (mut player, transform, team, entity, children) = query_local_player.single_mut();
-        if button_event.button_type == GamepadButtonType::South {
-            for child in children {
-                if let Ok(mut sprite) = query.get_mut(*child) {
-                    // TODO: check how to send even in bevy to DRY on actions
-                    if button_event.value != 0. {
-                        if player.cooldowns.sword.finished() {
-                            sprite.color = Color::rgb(0.25, 0.75, 0.25);
-                            let sword_entity = commands.spawn(SwordBundle::new(entity)).id();
-                            commands.entity(entity).add_child(sword_entity);
-                            player.cooldowns.sword.reset();
-                        }
-                    } else {
-                        sprite.color = DEFAULT_HAND_COLOR;
-                    }
-                }
-            }
+        let Ok(mut input) = query_local_player.get_single_mut() else {
+            return;
+        };
+        if button_event.button_type == GamepadButtonType::South && button_event.value != 0. {
+            input.sword = true;
         }
 
-        // TODO: check how to send even in bevy to DRY on actions
-        if button_event.button_type == GamepadButtonType::East
-            && button_event.value != 0.
-            && player.gold >= RACK_GOLD_VALUE
-        {
-            commands.spawn(RackBundle::new(team.clone(), *transform));
-            player.gold -= RACK_GOLD_VALUE;
+        if button_event.button_type == GamepadButtonType::East && button_event.value != 0. {
+            input.rack = true;
         }
     }
 }
@@ -271,7 +331,9 @@ fn update_ui(
     query_player: Query<&Player, With<LocalPlayer>>,
     mut query_ui: Query<&mut Text, With<GoldUI>>,
 ) {
-    let player = query_player.get_single().expect("no player found");
+    let Ok(player) = query_player.get_single() else {
+        return;
+    };
     let mut text = query_ui.get_single_mut().expect("no gold ui found");
 
     text.sections[0].value = format!("Gold: {}", player.gold);
@@ -280,10 +342,13 @@ fn update_ui(
 // maybe this is a bad idea to have a system per component since the collision event is having all contacts
 // it makes us loop inside collision events multiple time
 fn check_collisions_sword(
+    balance: Res<Balance>,
+    teams: Res<Teams>,
     query_swords: Query<(Entity, &Sword)>,
-    mut query_player: Query<(&mut Player, &Team)>,
-    mut query_hit_entities: Query<(Option<&Rewards>, &Team, &mut Health)>,
+    query_player: Query<&TeamId, With<Player>>,
+    query_hit_entities: Query<(Entity, Option<&TeamId>), With<Health>>,
     mut collision_events: EventReader<CollisionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
 ) {
     for collision_event in collision_events.read() {
         match collision_event {
@@ -297,33 +362,131 @@ fn check_collisions_sword(
                     Some(o) => o,
                 };
 
-                let health = if query_hit_entities.contains(*e1) {
-                    query_hit_entities.get_mut(*e1).ok()
-                } else {
-                    query_hit_entities.get_mut(*e2).ok()
-                };
-                let (rewards_opt, hit_team, mut health) = match health {
-                    None => continue,
-                    Some(o) => o,
+                let (target, target_team) = match query_hit_entities
+                    .get(*e1)
+                    .or_else(|_| query_hit_entities.get(*e2))
+                {
+                    Err(_) => continue,
+                    Ok(o) => o,
                 };
 
-                // hurt
-                if health.hit(20.).is_dead() {
-                    // player attached to this sword receive gold
-                    if let Ok((mut player, team)) = query_player.get_mut(sword.entity) {
-                        if let Some(rewards) = rewards_opt {
-                            if team.id != hit_team.id {
-                                player.gold += rewards.gold;
-                            }
-                        }
+                // allies are not hurt by swords
+                let team = query_player.get(sword.entity).ok().copied();
+                if let (Some(team), Some(target_team)) = (team, target_team) {
+                    if teams.is_ally(team, *target_team) {
+                        continue;
                     }
                 }
+
+                // hurt
+                damage_events.send(DamageEvent {
+                    target,
+                    source: Some(sword.entity),
+                    team,
+                    amount: balance.player.sword_damage,
+                    kind: DamageKind::Physical,
+                });
             }
             CollisionEvent::Stopped(_, _) => {}
         }
     }
 }
 
+// players receive the rewards of what they kill
+fn reward_kills(
+    teams: Res<Teams>,
+    mut stats: ResMut<TeamStats>,
+    mut damaged_events: EventReader<Damaged>,
+    mut query_players: Query<(&mut Player, &TeamId)>,
+    query_rewards: Query<(&Rewards, &TeamId)>,
+) {
+    for damaged in damaged_events.read() {
+        if !damaged.killed {
+            continue;
+        }
+        let Some(source) = damaged.damage.source else {
+            continue;
+        };
+        let Ok((mut player, team)) = query_players.get_mut(source) else {
+            continue;
+        };
+        let Ok((rewards, hit_team)) = query_rewards.get(damaged.damage.target) else {
+            continue;
+        };
+
+        if !teams.is_ally(*team, *hit_team) {
+            player.gold += rewards.gold;
+            stats.get_mut(*team).gold_earned += rewards.gold;
+        }
+    }
+}
+
+// dead players wait `respawn_secs`, out of the fight
+fn die(
+    mut commands: Commands,
+    balance: Res<Balance>,
+    mut died_events: EventReader<Died>,
+    query_players: Query<(), With<Player>>,
+) {
+    for died in died_events.read() {
+        if !query_players.contains(died.entity) {
+            continue;
+        }
+
+        info!("[player] {:?} died", died.entity);
+        commands.entity(died.entity).insert((
+            DeathDelay(Timer::from_seconds(
+                balance.player.respawn_secs,
+                TimerMode::Once,
+            )),
+            ColliderDisabled,
+            Visibility::Hidden,
+        ));
+    }
+}
+
+// back in front of the team castle, defeated players stay dead
+#[allow(clippy::type_complexity)]
+fn respawn(
+    mut commands: Commands,
+    time: Res<Time>,
+    query_castles: Query<(&Transform, &TeamId), (With<Castle>, Without<Dead>, Without<Player>)>,
+    mut query: Query<
+        (
+            Entity,
+            &mut DeathDelay,
+            &mut Health,
+            &mut Transform,
+            &TeamId,
+        ),
+        (With<Player>, With<Dead>, Without<Defeated>),
+    >,
+) {
+    for (entity, mut delay, mut health, mut transform, team) in &mut query {
+        if !delay.0.tick(time.delta()).finished() {
+            continue;
+        }
+        let Some((castle_transform, _)) = query_castles
+            .iter()
+            .find(|(_, castle_team)| *castle_team == team)
+        else {
+            continue;
+        };
+
+        let castle = castle_transform.translation.truncate();
+        let position = castle - castle.normalize_or_zero() * RESPAWN_DISTANCE;
+        transform.translation = position.extend(transform.translation.z);
+        health.value = health.max;
+        health.killer = None;
+
+        info!("[player] {:?} respawned", entity);
+        commands
+            .entity(entity)
+            .remove::<(Dead, DeathDelay, ColliderDisabled)>()
+            .insert(Visibility::Inherited);
+    }
+}
+
 fn update_sword(
     mut commands: Commands,
     time: Res<Time>,
@@ -342,30 +505,92 @@ fn update_cooldowns(time: Res<Time>, mut query_players: Query<&mut Player>) {
     }
 }
 
+fn apply_inputs(
+    mut commands: Commands,
+    time: Res<Time>,
+    balance: Res<Balance>,
+    teams: Res<Teams>,
+    mut stats: ResMut<TeamStats>,
+    mut query: Query<
+        (
+            &mut PlayerInput,
+            &mut Player,
+            &mut RngComponent,
+            &mut Transform,
+            &TeamId,
+            Entity,
+            Option<&StatusEffects>,
+        ),
+        // defeated and dead players only watch
+        (Without<Defeated>, Without<Dead>),
+    >,
+) {
+    for (mut input, mut player, mut rng, mut transform, team, entity, effects) in &mut query {
+        // stunned players lose their actions
+        if effects.is_some_and(StatusEffects::is_stunned) {
+            input.sword = false;
+            input.rack = false;
+            continue;
+        }
+
+        let movement = input.movement.clamp(Vec2::NEG_ONE, Vec2::ONE);
+        let speed = balance.player.speed * speed_factor(effects);
+        transform.translation.x += movement.x * speed * time.delta_seconds();
+        transform.translation.y += movement.y * speed * time.delta_seconds();
+
+        if let Some(rotation) = input.rotation {
+            transform.rotation = Quat::from_rotation_z(rotation);
+        }
+
+        if input.sword && player.cooldowns.sword.finished() {
+            let sword_entity = commands.spawn(SwordBundle::new(entity)).id();
+            commands.entity(entity).add_child(sword_entity);
+            player.cooldowns.sword.reset();
+        }
+
+        if input.rack && player.gold >= balance.rack.gold_value {
+            commands.spawn(RackBundle::new(
+                teams.get(*team),
+                *transform,
+                rng.fork(),
+                &balance,
+            ));
+            player.gold -= balance.rack.gold_value;
+
+            let stat = stats.get_mut(*team);
+            stat.racks_built += 1;
+            stat.gold_spent += balance.rack.gold_value;
+        }
+
+        // actions are consumed, movements are kept until devices update them
+        input.sword = false;
+        input.rack = false;
+    }
+}
+
 fn keyboard_movements(
     keyboard_input: Res<Input<KeyCode>>,
-    mut query_player: Query<&mut Transform, With<LocalPlayer>>,
-    time: Res<Time>,
+    mut query_player: Query<&mut PlayerInput, With<LocalPlayer>>,
 ) {
-    for mut transform in &mut query_player {
+    for mut input in &mut query_player {
         if keyboard_input.pressed(KeyCode::Left) || keyboard_input.pressed(KeyCode::A) {
-            transform.translation.x -= JOYSTICK_SCALE * time.delta_seconds();
+            input.movement.x -= 1.;
         }
         if keyboard_input.pressed(KeyCode::Right) || keyboard_input.pressed(KeyCode::D) {
-            transform.translation.x += JOYSTICK_SCALE * time.delta_seconds();
+            input.movement.x += 1.;
         }
         if keyboard_input.pressed(KeyCode::Down) || keyboard_input.pressed(KeyCode::S) {
-            transform.translation.y -= JOYSTICK_SCALE * time.delta_seconds();
+            input.movement.y -= 1.;
         }
         if keyboard_input.pressed(KeyCode::Up) || keyboard_input.pressed(KeyCode::W) {
-            transform.translation.y += JOYSTICK_SCALE * time.delta_seconds();
+            input.movement.y += 1.;
         }
     }
 }
 
 fn mouse_movements(
     primary_window: Query<&Window, With<PrimaryWindow>>,
-    mut query_player: Query<&mut Transform, With<LocalPlayer>>,
+    mut query_player: Query<(&mut PlayerInput, &Transform), With<LocalPlayer>>,
     query_camera: Query<&Transform, (Without<LocalPlayer>, With<Camera>)>,
 ) {
     let Ok(window) = primary_window.get_single() else {
@@ -376,7 +601,7 @@ fn mouse_movements(
     };
 
     if let Some(cursor_position) = window.cursor_position() {
-        if let Ok(mut player_transform) = query_player.get_single_mut() {
+        if let Ok((mut input, player_transform)) = query_player.get_single_mut() {
             let window_half_size = Vec2::new(window.width(), window.height()) / 2.;
             let cursor_position = Vec2::new(
                 cursor_position.x - window_half_size.x + camera_transform.translation.x,
@@ -385,45 +610,49 @@ fn mouse_movements(
             let pos = player_transform.translation.truncate(); // player position
 
             let direction = cursor_position - pos;
-            player_transform.rotation = Quat::from_rotation_z((-direction.x).atan2(direction.y));
+            input.rotation = Some((-direction.x).atan2(direction.y));
         }
     }
 }
 
 fn mouse_actions(
-    mut commands: Commands,
     buttons: Res<Input<MouseButton>>,
-    mut query_local_player: Query<(&mut Player, Entity, &Children), With<LocalPlayer>>,
-    mut query: Query<&mut Sprite, With<Hand>>,
+    mut query_local_player: Query<&mut PlayerInput, With<LocalPlayer>>,
 ) {
-    let (mut player, entity, children) = query_local_player.single_mut();
-
-    for child in children {
-        if let Ok(mut sprite) = query.get_mut(*child) {
-            // TODO: check how to send even in bevy to DRY on actions
-            if buttons.just_pressed(MouseButton::Left) {
-                if player.cooldowns.sword.finished() {
-                    sprite.color = Color::rgb(0.25, 0.75, 0.25);
-                    let sword_entity = commands.spawn(SwordBundle::new(entity)).id();
-                    commands.entity(entity).add_child(sword_entity);
-                    player.cooldowns.sword.reset();
-                }
-            } else {
-                sprite.color = DEFAULT_HAND_COLOR;
-            }
+    if buttons.just_pressed(MouseButton::Left) {
+        for mut input in &mut query_local_player {
+            input.sword = true;
         }
     }
 }
 
 fn keyboard_actions(
     keyboard_input: Res<Input<KeyCode>>,
-    mut commands: Commands,
-    mut query_local_player: Query<(&mut Player, &Transform, &Team), With<LocalPlayer>>,
+    mut query_local_player: Query<&mut PlayerInput, With<LocalPlayer>>,
 ) {
-    let (mut player, transform, team) = query_local_player.single_mut();
+    if keyboard_input.just_pressed(KeyCode::E) {
+        for mut input in &mut query_local_player {
+            input.rack = true;
+        }
+    }
+}
 
-    if keyboard_input.just_pressed(KeyCode::E) && player.gold >= RACK_GOLD_VALUE {
-        commands.spawn(RackBundle::new(team.clone(), *transform));
-        player.gold -= RACK_GOLD_VALUE;
+// the hand is green while the sword is out
+fn update_hand(
+    query_local_player: Query<&Children, With<LocalPlayer>>,
+    query_swords: Query<(), With<Sword>>,
+    mut query: Query<&mut Sprite, With<Hand>>,
+) {
+    for children in &query_local_player {
+        let attacking = children.iter().any(|child| query_swords.contains(*child));
+        for child in children {
+            if let Ok(mut sprite) = query.get_mut(*child) {
+                sprite.color = if attacking {
+                    Color::rgb(0.25, 0.75, 0.25)
+                } else {
+                    DEFAULT_HAND_COLOR
+                };
+            }
+        }
     }
 }
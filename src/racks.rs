@@ -7,42 +7,53 @@ use bevy::{
 };
 use bevy_rapier2d::prelude::*;
 use bevy_turborand::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::Rewards,
-    health::Health,
-    minions::MinionBundle,
-    teams::{Team, Teams},
+    balance::Balance,
+    common::{GameSet, Rewards},
+    effects::{speed_factor, StatusEffects},
+    health::{DespawnOnDeath, Health, Resistances},
+    minions::{MinionBundle, MinionKind},
+    navigation::NavObstacle,
+    state::MatchEntity,
+    stats::TeamStats,
+    teams::{Team, TeamId, Teams},
 };
 
-use rand::Rng;
-
-pub const RACK_GOLD_VALUE: f32 = 10.;
-
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Rack {
     pub minion_spawn_timer: Timer,
     pub minion_spawn_timer_q: Timer,
     pub minion_spawn_count: u32,
     pub minion_spawned_count: u32,
     pub minion_spawning: bool,
+    /// kind of the minions spawned
+    #[serde(default)]
+    pub minion_kind: MinionKind,
 }
 
 #[derive(Bundle)]
 pub struct RackBundle {
     pub sprite_bundle: SpriteBundle,
-    pub team: Team,
+    pub team: TeamId,
     pub rack: Rack,
+    pub match_entity: MatchEntity,
     pub health: Health,
+    pub resistances: Resistances,
+    pub despawn_on_death: DespawnOnDeath,
     pub rewards: Rewards,
+    pub nav_obstacle: NavObstacle,
     pub rigid_body: RigidBody,
     pub collider: Collider,
+    pub velocity: Velocity,
     pub events: ActiveEvents,
     pub mass: ColliderMassProperties,
+    pub rng: RngComponent,
 }
 
 impl RackBundle {
-    pub fn new(team: Team, transform: Transform) -> Self {
+    pub fn new(team: &Team, transform: Transform, rng: RngComponent, balance: &Balance) -> Self {
         let size = Vec2::new(20.0, 20.0);
         let mut minion_spawn_timer = Timer::from_seconds(1.5, TimerMode::Repeating);
         minion_spawn_timer.set_elapsed(Duration::from_secs_f32(1.0));
@@ -56,74 +67,70 @@ impl RackBundle {
                 transform,
                 ..default()
             },
-            team,
+            team: team.id,
             rack: Rack {
                 minion_spawning: false,
                 minion_spawned_count: 0,
                 minion_spawn_count: 5,
                 minion_spawn_timer,
                 minion_spawn_timer_q: Timer::from_seconds(0.2, TimerMode::Repeating),
+                minion_kind: MinionKind::default(),
             },
-            health: Health::new(220.)
+            match_entity: MatchEntity,
+            health: Health::new(balance.rack.health)
                 .with_health_bar_position(Vec3::new(0.0, 20.0, 0.0))
                 .with_health_bar_size(Vec2::new(size.x, 5.)),
-            rewards: Rewards { gold: 100. },
+            resistances: balance.rack.resistances,
+            despawn_on_death: DespawnOnDeath,
+            rewards: Rewards {
+                gold: balance.rack.rewards_gold,
+            },
+            nav_obstacle: NavObstacle,
             rigid_body: RigidBody::Dynamic,
             collider: Collider::cuboid((size.x / 2.) * 0.98, (size.y / 2.) * 0.98),
+            velocity: Velocity::zero(),
             events: ActiveEvents::COLLISION_EVENTS,
             mass: ColliderMassProperties::Mass(0.),
+            rng,
         }
     }
+
+    pub fn with_minion_kind(mut self, kind: MinionKind) -> Self {
+        self.rack.minion_kind = kind;
+        self
+    }
 }
 
 pub struct RacksPlugin;
 
 impl Plugin for RacksPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup)
-            .add_systems(Update, spawn_minions)
-            .add_systems(PostUpdate, destroy);
-    }
-}
-
-fn setup(mut commands: Commands, teams: Res<Teams>, mut rand: ResMut<GlobalRng>) {
-    let rand_count = rand.get_mut().fork();
-    let mut keys: Vec<&String> = teams.map.keys().collect();
-    keys.sort();
-
-    for id in keys {
-        let Some(t) = teams.get(id.into()) else {
-            continue;
-        };
-
-        let count = rand_count.u32(2..5);
-        let rand_pos = rand_count.fork();
-        for _ in 0..count {
-            commands.spawn(RackBundle::new(
-                t.clone(),
-                Transform::from_xyz(
-                    rand_pos.f32_normalized() * 500.,
-                    rand_pos.f32_normalized() * 300.,
-                    0.,
-                ),
-            ));
-        }
+        app.add_systems(FixedUpdate, spawn_minions.in_set(GameSet::Logic));
     }
 }
 
 fn spawn_minions(
     mut commands: Commands,
     time: Res<Time>,
-    mut query: Query<(&mut Rack, &Collider, &Transform, &Team)>,
+    teams: Res<Teams>,
+    mut stats: ResMut<TeamStats>,
+    mut query: Query<(
+        &mut Rack,
+        &mut RngComponent,
+        &Collider,
+        &Transform,
+        &TeamId,
+        Option<&StatusEffects>,
+    )>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    balance: Res<Balance>,
 ) {
-    let mut rng: rand::rngs::ThreadRng = rand::thread_rng();
-
-    for (mut rack, collider, transform, team) in &mut query {
-        // ticks timers
-        rack.minion_spawn_timer_q.tick(time.delta());
-        rack.minion_spawn_timer.tick(time.delta());
+    for (mut rack, mut rng, collider, transform, team, effects) in &mut query {
+        // ticks timers, slower when slowed, not at all when stunned
+        let delta = time.delta().mul_f32(speed_factor(effects));
+        rack.minion_spawn_timer_q.tick(delta);
+        rack.minion_spawn_timer.tick(delta);
 
         // we are ready to start spawning
         if rack.minion_spawn_timer.just_finished() {
@@ -140,27 +147,31 @@ fn spawn_minions(
         {
             // TODO: should RNG an angle instead
             if let Some(cuboid) = collider.as_cuboid() {
-                let mut offset_x = cuboid.half_extents().x + rng.gen_range(2.0..10.0);
-                let mut offset_y = cuboid.half_extents().y + rng.gen_range(2.0..10.0);
+                let mut offset_x = cuboid.half_extents().x + 2. + rng.f32() * 8.;
+                let mut offset_y = cuboid.half_extents().y + 2. + rng.f32() * 8.;
 
-                if rng.gen_bool(0.5) {
+                if rng.bool() {
                     offset_x *= -1.;
                 }
-                if rng.gen_bool(0.5) {
+                if rng.bool() {
                     offset_y *= -1.;
                 }
 
                 commands.spawn(MinionBundle::new(
                     &mut meshes,
                     &mut materials,
+                    &balance,
                     Vec3::new(
                         transform.translation.x + offset_x,
                         transform.translation.y + offset_y,
                         transform.translation.z,
                     ),
-                    team.clone(),
+                    teams.get(*team),
+                    rack.minion_kind,
+                    rng.fork(),
                 ));
                 rack.minion_spawned_count += 1;
+                stats.get_mut(*team).minions_spawned += 1;
 
                 if rack.minion_spawned_count >= rack.minion_spawn_count {
                     debug!("[rack] every minions are spawned!");
@@ -170,18 +181,3 @@ fn spawn_minions(
         }
     }
 }
-
-// TODO: maybe this system can be retrieve from health bar crate (give the type and insert it in the filter?)
-fn destroy(mut commands: Commands, mut query: Query<(&Health, Entity), With<Rack>>) {
-    let mut kill = |entity| {
-        trace!("Unspawning Minion: {:?}", entity);
-        commands.entity(entity).despawn_recursive();
-    };
-
-    for (health, entity) in &mut query {
-        // just not enough health
-        if health.is_dead() {
-            kill(entity);
-        }
-    }
-}
@@ -0,0 +1,198 @@
+use std::{fs, io};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    common::{GameSet, Tick},
+    config::GameConfig,
+    player::{ApplyInputsSet, LocalPlayer, PlayerInput},
+    state::GameState,
+};
+
+/// Everything needed to play a match again: how it was launched and the local player inputs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Replay {
+    pub seed: String,
+    pub map: Option<String>,
+    pub teams: usize,
+    /// only ticks where the input changed are recorded
+    pub inputs: Vec<ReplayInput>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayInput {
+    pub tick: u64,
+    pub input: PlayerInput,
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("could not read or write replay: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not parse replay: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize replay: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+impl Replay {
+    pub fn new(config: &GameConfig) -> Self {
+        Self {
+            seed: config.seed.clone(),
+            map: config.map.clone(),
+            teams: config.teams,
+            inputs: vec![],
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, ReplayError> {
+        let bytes = fs::read(path)?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ReplayError> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Launches the game the same way the replay was recorded.
+    pub fn configure(&self, config: &mut GameConfig) {
+        config.seed = self.seed.clone();
+        config.map = self.map.clone();
+        config.teams = self.teams;
+    }
+}
+
+/// Replaces live inputs of the local player with the replay ones.
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    next: usize,
+    current: PlayerInput,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next: 0,
+            current: PlayerInput::default(),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ReplayRecorder {
+    path: String,
+    replay: Replay,
+    last: PlayerInput,
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(
+                OnExit(GameState::MainMenu),
+                (
+                    rewind.run_if(resource_exists::<ReplayPlayback>()),
+                    restart_recording.run_if(resource_exists::<ReplayRecorder>()),
+                ),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    play.run_if(resource_exists::<ReplayPlayback>()),
+                    record.run_if(resource_exists::<ReplayRecorder>()),
+                )
+                    .chain()
+                    .in_set(GameSet::Logic)
+                    .before(ApplyInputsSet),
+            )
+            .add_systems(Last, save.run_if(resource_exists::<ReplayRecorder>()));
+    }
+}
+
+fn setup(mut commands: Commands, config: Res<GameConfig>) {
+    if let Some(path) = &config.record {
+        info!("[replay] recording into {}", path);
+        commands.insert_resource(ReplayRecorder {
+            path: path.clone(),
+            replay: Replay::new(&config),
+            last: PlayerInput::default(),
+        });
+    }
+}
+
+// every match plays the replay from the start
+fn rewind(mut playback: ResMut<ReplayPlayback>) {
+    playback.next = 0;
+    playback.current = PlayerInput::default();
+}
+
+// only the last match is recorded
+fn restart_recording(mut recorder: ResMut<ReplayRecorder>) {
+    recorder.replay.inputs.clear();
+    recorder.last = PlayerInput::default();
+}
+
+fn play(
+    tick: Res<Tick>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut query: Query<&mut PlayerInput, With<LocalPlayer>>,
+) {
+    while let Some(frame) = playback.replay.inputs.get(playback.next) {
+        if frame.tick > tick.0 {
+            break;
+        }
+        playback.current = frame.input.clone();
+        playback.next += 1;
+    }
+
+    for mut input in &mut query {
+        *input = playback.current.clone();
+    }
+
+    // actions only last one tick
+    playback.current.sword = false;
+    playback.current.rack = false;
+}
+
+fn record(
+    tick: Res<Tick>,
+    mut recorder: ResMut<ReplayRecorder>,
+    query: Query<&PlayerInput, With<LocalPlayer>>,
+) {
+    let Ok(input) = query.get_single() else {
+        return;
+    };
+
+    if *input != recorder.last {
+        recorder.replay.inputs.push(ReplayInput {
+            tick: tick.0,
+            input: input.clone(),
+        });
+    }
+
+    // actions are consumed by this tick
+    recorder.last = PlayerInput {
+        sword: false,
+        rack: false,
+        ..input.clone()
+    };
+}
+
+fn save(recorder: Res<ReplayRecorder>, mut exit_events: EventReader<AppExit>) {
+    if exit_events.read().next().is_none() {
+        return;
+    }
+
+    match recorder.replay.save(&recorder.path) {
+        Ok(()) => info!("[replay] saved into {}", recorder.path),
+        Err(error) => error!("[replay] {}", error),
+    }
+}
@@ -0,0 +1,548 @@
+use std::{fs, io};
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_turborand::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    balance::Balance,
+    castles::{Castle, CastleBundle},
+    common::{Rewards, Tick, TimeDestroyable, WorldBounds},
+    config::GameConfig,
+    effects::{Effect, StatusEffect, StatusEffects},
+    health::{Dead, DeathDelay, Health, Regeneration},
+    maps::{Obstacle, ObstacleBundle},
+    minions::{Minion, MinionBundle, MinionKind, Projectile, ProjectileBundle},
+    physics::{Collisions, EntityPair},
+    player::Player,
+    racks::{Rack, RackBundle},
+    state::GameState,
+    teams::{Relation, Team, TeamId, Teams},
+    victory::Defeated,
+};
+
+pub const DEFAULT_SNAPSHOT_PATH: &str = "snapshot.ron";
+
+/// The whole match at a given tick.
+///
+/// Explosions and swords only live a few ticks and are not saved,
+/// neither are the physics engine internals (contacts are restored through `Collisions`).
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub rng: GlobalRng,
+    pub bounds: Vec2,
+    pub teams: Vec<Team>,
+    #[serde(default)]
+    pub relations: Vec<(TeamId, TeamId, Relation)>,
+    pub entities: Vec<SnapshotEntity>,
+    /// pairs of indices in `entities`
+    pub collisions: Vec<(usize, usize)>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum SnapshotKind {
+    Player,
+    Castle,
+    Rack,
+    Minion,
+    Projectile,
+    Obstacle { size: Vec2 },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotEntity {
+    pub kind: SnapshotKind,
+    pub transform: Transform,
+    pub linvel: Vec2,
+    pub angvel: f32,
+    pub team: Option<TeamId>,
+    pub health: Option<Health>,
+    pub rack: Option<Rack>,
+    pub minion: Option<Minion>,
+    pub player: Option<Player>,
+    pub rewards: Option<Rewards>,
+    pub rng: Option<RngComponent>,
+    pub lifetime: Option<TimeDestroyable>,
+    /// time left before a dead player respawns
+    #[serde(default)]
+    pub death_delay: Option<Timer>,
+    /// with the seconds since the last damage taken, which the balance does not have
+    #[serde(default)]
+    pub regeneration: Option<(Regeneration, f32)>,
+    /// in the order they were applied
+    #[serde(default)]
+    pub effects: Vec<SnapshotEffect>,
+    #[serde(default)]
+    pub projectile: Option<SnapshotProjectile>,
+}
+
+/// A `StatusEffect`, its source is an index in `Snapshot::entities`.
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotEffect {
+    pub effect: Effect,
+    pub remaining_secs: f32,
+    pub source: Option<usize>,
+    pub team: Option<TeamId>,
+}
+
+/// A `Projectile`, its source is an index in `Snapshot::entities`.
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotProjectile {
+    pub damage: f32,
+    pub velocity: Vec2,
+    pub source: Option<usize>,
+}
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("could not read or write snapshot: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not parse snapshot: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize snapshot: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+impl Snapshot {
+    pub fn load(path: &str) -> Result<Self, SnapshotError> {
+        let bytes = fs::read(path)?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), SnapshotError> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// Saves or restores the match, between two ticks.
+#[derive(Event, Clone)]
+pub enum SnapshotCommand {
+    Save(String),
+    Load(String),
+}
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SnapshotCommand>()
+            .add_systems(OnExit(GameState::MainMenu), load_from_config)
+            .add_systems(
+                Update,
+                (keyboard_commands, load)
+                    .chain()
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(Last, save);
+    }
+}
+
+// only the first match starts from the snapshot
+fn load_from_config(mut config: ResMut<GameConfig>, mut commands: EventWriter<SnapshotCommand>) {
+    if let Some(path) = config.load.take() {
+        commands.send(SnapshotCommand::Load(path));
+    }
+}
+
+// F5 saves, F9 loads
+fn keyboard_commands(
+    keyboard_input: Option<Res<Input<KeyCode>>>,
+    mut commands: EventWriter<SnapshotCommand>,
+) {
+    let Some(keyboard_input) = keyboard_input else {
+        return;
+    };
+
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        commands.send(SnapshotCommand::Save(DEFAULT_SNAPSHOT_PATH.to_string()));
+    }
+    if keyboard_input.just_pressed(KeyCode::F9) {
+        commands.send(SnapshotCommand::Load(DEFAULT_SNAPSHOT_PATH.to_string()));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn save(
+    mut snapshot_commands: EventReader<SnapshotCommand>,
+    tick: Res<Tick>,
+    rng: Res<GlobalRng>,
+    bounds: Res<WorldBounds>,
+    teams: Option<Res<Teams>>,
+    collisions: Res<Collisions>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&Velocity>,
+            Option<&TeamId>,
+            Option<&Health>,
+            Option<&Rack>,
+            Option<&Minion>,
+            Option<&Player>,
+            Option<&Rewards>,
+            Option<&RngComponent>,
+            Option<&TimeDestroyable>,
+            Option<&Castle>,
+            Option<&Sprite>,
+            Option<&DeathDelay>,
+            (
+                Option<&StatusEffects>,
+                Option<&Projectile>,
+                Option<&Regeneration>,
+            ),
+        ),
+        Or<(
+            With<Player>,
+            With<Castle>,
+            With<Rack>,
+            With<Minion>,
+            With<Projectile>,
+            With<Obstacle>,
+        )>,
+    >,
+) {
+    for command in snapshot_commands.read() {
+        let SnapshotCommand::Save(path) = command else {
+            continue;
+        };
+
+        let mut rows: Vec<_> = query.iter().collect();
+        rows.sort_by_key(|row| row.0);
+
+        let entities: Vec<Entity> = rows.iter().map(|row| row.0).collect();
+        let index = |entity: Entity| entities.iter().position(|e| *e == entity);
+
+        let relations = teams
+            .as_ref()
+            .map(|teams| teams.relations())
+            .unwrap_or_default();
+        let teams: Vec<Team> = teams
+            .as_ref()
+            .map(|teams| teams.iter().cloned().collect())
+            .unwrap_or_default();
+
+        let mut collisions: Vec<(usize, usize)> = collisions
+            .pairs
+            .keys()
+            .filter_map(|pair| Some((index(pair.entity1)?, index(pair.entity2)?)))
+            .collect();
+        collisions.sort();
+
+        let snapshot = Snapshot {
+            tick: tick.0,
+            rng: rng.clone(),
+            bounds: bounds.size,
+            teams,
+            relations,
+            entities: rows
+                .iter()
+                .map(
+                    |(
+                        _,
+                        transform,
+                        velocity,
+                        team,
+                        health,
+                        rack,
+                        minion,
+                        player,
+                        rewards,
+                        rng,
+                        lifetime,
+                        castle,
+                        sprite,
+                        death_delay,
+                        (effects, projectile, regeneration),
+                    )| {
+                        let kind = if player.is_some() {
+                            SnapshotKind::Player
+                        } else if castle.is_some() {
+                            SnapshotKind::Castle
+                        } else if rack.is_some() {
+                            SnapshotKind::Rack
+                        } else if minion.is_some() {
+                            SnapshotKind::Minion
+                        } else if projectile.is_some() {
+                            SnapshotKind::Projectile
+                        } else {
+                            SnapshotKind::Obstacle {
+                                size: sprite.and_then(|s| s.custom_size).unwrap_or_default(),
+                            }
+                        };
+
+                        SnapshotEntity {
+                            kind,
+                            transform: **transform,
+                            linvel: velocity.map(|v| v.linvel).unwrap_or_default(),
+                            angvel: velocity.map(|v| v.angvel).unwrap_or_default(),
+                            team: team.copied(),
+                            health: health.cloned(),
+                            rack: rack.cloned(),
+                            minion: minion.cloned(),
+                            player: player.cloned(),
+                            rewards: rewards.cloned(),
+                            rng: rng.cloned(),
+                            lifetime: lifetime.cloned(),
+                            death_delay: death_delay.map(|delay| delay.0.clone()),
+                            regeneration: regeneration
+                                .map(|regeneration| (*regeneration, regeneration.idle_secs)),
+                            effects: effects
+                                .iter()
+                                .flat_map(|effects| effects.iter())
+                                .map(|current| SnapshotEffect {
+                                    effect: current.effect,
+                                    remaining_secs: current.remaining_secs,
+                                    source: current.source.and_then(index),
+                                    team: current.team,
+                                })
+                                .collect(),
+                            projectile: projectile.map(|projectile| SnapshotProjectile {
+                                damage: projectile.damage(),
+                                velocity: projectile.velocity(),
+                                source: projectile.source().and_then(index),
+                            }),
+                        }
+                    },
+                )
+                .collect(),
+            collisions,
+        };
+
+        match snapshot.save(path) {
+            Ok(()) => info!("[snapshot] saved into {}", path),
+            Err(error) => error!("[snapshot] {}", error),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn load(
+    mut commands: Commands,
+    mut snapshot_commands: EventReader<SnapshotCommand>,
+    balance: Res<Balance>,
+    mut global_rng: ResMut<GlobalRng>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query_match: Query<Entity, (Or<(With<TeamId>, With<Obstacle>)>, Without<Player>)>,
+    query_players: Query<Entity, With<Player>>,
+) {
+    for command in snapshot_commands.read() {
+        let SnapshotCommand::Load(path) = command else {
+            continue;
+        };
+
+        let snapshot = match Snapshot::load(path) {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                error!("[snapshot] {}: {}", path, error);
+                continue;
+            }
+        };
+
+        for entity in &query_match {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        // ids are given in order, as they were saved
+        let mut teams = Teams::new();
+        for team in &snapshot.teams {
+            let id = teams.add(team.name.clone(), team.color);
+            if team.eliminated {
+                teams.eliminate(id);
+            }
+        }
+        for (a, b, relation) in &snapshot.relations {
+            if usize::from(a.0.max(b.0)) >= teams.len() {
+                warn!("[snapshot] unknown team in relation {} / {}", a.0, b.0);
+                continue;
+            }
+            teams.set_relation(*a, *b, *relation);
+        }
+
+        // players are updated in place, the camera and the inputs follow them
+        let mut players = query_players.iter();
+        let mut entities: Vec<Option<Entity>> = Vec::with_capacity(snapshot.entities.len());
+        // they point to other entities, restored once every entity is spawned
+        let mut effects: Vec<(Entity, Vec<SnapshotEffect>)> = vec![];
+        let mut projectiles: Vec<(Entity, SnapshotProjectile)> = vec![];
+
+        for saved in snapshot.entities {
+            let team = saved
+                .team
+                .filter(|team| usize::from(team.0) < teams.len())
+                .map(|team| teams.get(team));
+            let rng = saved
+                .rng
+                .clone()
+                .unwrap_or_else(|| RngComponent::from(&mut global_rng));
+
+            let entity = match (&saved.kind, team) {
+                (SnapshotKind::Player, _) => players.next(),
+                (SnapshotKind::Castle, Some(team)) => Some(
+                    commands
+                        .spawn(CastleBundle::new(team, saved.transform, rng, &balance))
+                        .id(),
+                ),
+                (SnapshotKind::Rack, Some(team)) => Some(
+                    commands
+                        .spawn(RackBundle::new(team, saved.transform, rng, &balance))
+                        .id(),
+                ),
+                (SnapshotKind::Minion, Some(team)) => Some(
+                    commands
+                        .spawn(MinionBundle::new(
+                            &mut meshes,
+                            &mut materials,
+                            &balance,
+                            saved.transform.translation,
+                            team,
+                            saved
+                                .minion
+                                .as_ref()
+                                .map_or(MinionKind::default(), Minion::kind),
+                            rng,
+                        ))
+                        .id(),
+                ),
+                (SnapshotKind::Projectile, Some(team)) => {
+                    saved.projectile.as_ref().map(|projectile| {
+                        commands
+                            .spawn(ProjectileBundle::new(
+                                &mut meshes,
+                                &mut materials,
+                                None,
+                                saved.transform.translation,
+                                projectile.velocity,
+                                // restored with the other timers below
+                                0.,
+                                team,
+                                projectile.damage,
+                            ))
+                            .id()
+                    })
+                }
+                (SnapshotKind::Obstacle { size }, _) => Some(
+                    commands
+                        .spawn(ObstacleBundle::new(
+                            saved.transform.translation.truncate(),
+                            *size,
+                        ))
+                        .id(),
+                ),
+                _ => None,
+            };
+            entities.push(entity);
+
+            let Some(entity) = entity else {
+                warn!("[snapshot] could not restore an entity");
+                continue;
+            };
+
+            let mut cmd = commands.entity(entity);
+            cmd.insert((
+                saved.transform,
+                Velocity {
+                    linvel: saved.linvel,
+                    angvel: saved.angvel,
+                },
+            ));
+            if matches!(saved.kind, SnapshotKind::Player) {
+                // alive unless saved dead, their death was already handled
+                cmd.remove::<(Dead, DeathDelay, ColliderDisabled, StatusEffects, Defeated)>()
+                    .insert(Visibility::Inherited);
+                if saved.health.as_ref().is_some_and(Health::is_dead) {
+                    cmd.insert((Dead, ColliderDisabled, Visibility::Hidden));
+                }
+                // teams are only eliminated once, their players stay defeated
+                if team.is_some_and(|team| team.eliminated) {
+                    cmd.insert(Defeated);
+                }
+            }
+            if let Some(delay) = saved.death_delay {
+                cmd.insert(DeathDelay(delay));
+            }
+            if let Some((mut regeneration, idle_secs)) = saved.regeneration {
+                regeneration.idle_secs = idle_secs;
+                cmd.insert(regeneration);
+            }
+            if let Some(team) = saved.team {
+                cmd.insert(team);
+            }
+            if let Some(mut health) = saved.health {
+                // players keep their health bar
+                health.add_health_bar = !matches!(saved.kind, SnapshotKind::Player);
+                cmd.insert(health);
+            }
+            if let Some(rack) = saved.rack {
+                cmd.insert(rack);
+            }
+            if let Some(minion) = saved.minion {
+                cmd.insert(minion);
+            }
+            if let Some(player) = saved.player {
+                cmd.insert(player);
+            }
+            if let Some(rewards) = saved.rewards {
+                cmd.insert(rewards);
+            }
+            if let Some(rng) = saved.rng {
+                cmd.insert(rng);
+            }
+            if let Some(lifetime) = saved.lifetime {
+                cmd.insert(lifetime);
+            }
+            if !saved.effects.is_empty() {
+                effects.push((entity, saved.effects));
+            }
+            if let Some(projectile) = saved.projectile {
+                projectiles.push((entity, projectile));
+            }
+        }
+
+        let resolve = |index: Option<usize>| index.and_then(|index| *entities.get(index)?);
+        for (entity, saved) in effects {
+            let mut restored = StatusEffects::default();
+            // stacking rules give back the same effects
+            for current in saved {
+                restored.add(StatusEffect {
+                    effect: current.effect,
+                    remaining_secs: current.remaining_secs,
+                    source: resolve(current.source),
+                    team: current.team,
+                });
+            }
+            commands.entity(entity).insert(restored);
+        }
+        for (entity, saved) in projectiles {
+            commands.entity(entity).insert(Projectile::new(
+                saved.damage,
+                saved.velocity,
+                resolve(saved.source),
+            ));
+        }
+
+        let mut collisions = Collisions::new();
+        for (index1, index2) in snapshot.collisions {
+            if let (Some(Some(entity1)), Some(Some(entity2))) =
+                (entities.get(index1), entities.get(index2))
+            {
+                collisions.add(EntityPair::new(*entity1, *entity2));
+            }
+        }
+
+        commands.insert_resource(collisions);
+        commands.insert_resource(teams);
+        commands.insert_resource(snapshot.rng);
+        commands.insert_resource(WorldBounds {
+            size: snapshot.bounds,
+        });
+        commands.insert_resource(Tick(snapshot.tick));
+
+        info!("[snapshot] loaded from {}", path);
+    }
+}
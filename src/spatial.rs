@@ -0,0 +1,334 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    common::GameSet,
+    health::{Dead, Health},
+    teams::{TeamId, Teams},
+};
+
+/// Side of a grid cell, about the distance a minion covers in a second.
+pub const SPATIAL_CELL_SIZE: f32 = 200.;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SpatialEntry {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub team: TeamId,
+}
+
+/// Units of every team (anything with health and a team) bucketed by cell,
+/// rebuilt every tick in `GameSet::Collisions`.
+///
+/// Lookups only visit cells around the position, instead of every unit.
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    entries: Vec<SpatialEntry>,
+    // indexes in `entries`, in insertion order
+    cells: HashMap<IVec2, Vec<usize>>,
+    // occupied cells are within these
+    min_cell: IVec2,
+    max_cell: IVec2,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(SPATIAL_CELL_SIZE)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            entries: vec![],
+            cells: HashMap::default(),
+            min_cell: IVec2::ZERO,
+            max_cell: IVec2::ZERO,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        // keeps the cells allocated, units rarely move far in a tick
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        self.min_cell = IVec2::ZERO;
+        self.max_cell = IVec2::ZERO;
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2, team: TeamId) {
+        let cell = self.cell(position);
+        if self.entries.is_empty() {
+            self.min_cell = cell;
+            self.max_cell = cell;
+        } else {
+            self.min_cell = self.min_cell.min(cell);
+            self.max_cell = self.max_cell.max(cell);
+        }
+
+        self.cells.entry(cell).or_default().push(self.entries.len());
+        self.entries.push(SpatialEntry {
+            entity,
+            position,
+            team,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Units within `radius` of `position` (can be `f32::INFINITY`), whatever their team.
+    pub fn within(&self, position: Vec2, radius: f32) -> impl Iterator<Item = &SpatialEntry> + '_ {
+        // no occupied cell out of these, an infinite radius visits them all
+        let min = self.cell(position - radius).max(self.min_cell);
+        let max = self.cell(position + radius).min(self.max_cell);
+        let radius_squared = radius * radius;
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(move |cell| self.cells.get(&cell))
+            .flat_map(move |indexes| indexes.iter().map(move |index| &self.entries[*index]))
+            .filter(move |entry| entry.position.distance_squared(position) <= radius_squared)
+    }
+
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    /// The closest unit hostile to `team` within `radius` (can be `f32::INFINITY`).
+    ///
+    /// Cells are visited in rings around the position, ties go to the first unit inserted.
+    pub fn nearest_hostile(
+        &self,
+        teams: &Teams,
+        position: Vec2,
+        team: TeamId,
+        radius: f32,
+    ) -> Option<SpatialEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let center = self.cell(position);
+        // no occupied cell past this ring
+        let last_ring = (center - self.min_cell)
+            .max(self.max_cell - center)
+            .max_element()
+            .max(0);
+        let last_ring = if radius.is_finite() {
+            last_ring.min((radius / self.cell_size).ceil() as i32 + 1)
+        } else {
+            last_ring
+        };
+        let radius_squared = radius * radius;
+
+        let mut nearest: Option<(f32, &SpatialEntry)> = None;
+        for ring in 0..=last_ring {
+            // units of this ring are at least this far, nothing closer can be found
+            if let Some((distance_squared, _)) = nearest {
+                let ring_distance = (ring - 1).max(0) as f32 * self.cell_size;
+                if ring_distance * ring_distance > distance_squared {
+                    break;
+                }
+            }
+
+            for cell in ring_cells(center, ring) {
+                let Some(indexes) = self.cells.get(&cell) else {
+                    continue;
+                };
+                for entry in indexes.iter().map(|index| &self.entries[*index]) {
+                    if !teams.is_hostile(team, entry.team) {
+                        continue;
+                    }
+                    let distance_squared = position.distance_squared(entry.position);
+                    if distance_squared > radius_squared {
+                        continue;
+                    }
+                    if nearest.map_or(true, |(nearest_squared, _)| {
+                        distance_squared < nearest_squared
+                    }) {
+                        nearest = Some((distance_squared, entry));
+                    }
+                }
+            }
+        }
+
+        nearest.map(|(_, entry)| *entry)
+    }
+}
+
+// cells at exactly `ring` cells from `center`, row by row
+fn ring_cells(center: IVec2, ring: i32) -> impl Iterator<Item = IVec2> {
+    (-ring..=ring).flat_map(move |y| {
+        // inner rows only have their two ends on the ring
+        let step = if y.abs() == ring {
+            1
+        } else {
+            (2 * ring).max(1) as usize
+        };
+        (-ring..=ring)
+            .step_by(step)
+            .map(move |x| center + IVec2::new(x, y))
+    })
+}
+
+pub struct SpatialPlugin;
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialGrid>()
+            .add_systems(FixedUpdate, rebuild.in_set(GameSet::Collisions));
+    }
+}
+
+fn rebuild(
+    mut grid: ResMut<SpatialGrid>,
+    query: Query<(Entity, &Transform, &TeamId), (With<Health>, Without<Dead>)>,
+) {
+    grid.clear();
+    for (entity, transform, team) in &query {
+        grid.insert(entity, transform.translation.truncate(), *team);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_turborand::prelude::*;
+
+    use super::*;
+    use crate::teams::Relation;
+
+    const UNITS: u32 = 400;
+    const WORLD_SIZE: f32 = 2000.;
+
+    // within a `size` square centered on the origin
+    fn random_position(rng: &mut RngComponent, size: f32) -> Vec2 {
+        Vec2::new(rng.f32_normalized(), rng.f32_normalized()) * size / 2.
+    }
+
+    // distance squared to the nearest hostile unit, checking every one
+    fn brute_force(
+        units: &[(Entity, Vec2, TeamId)],
+        teams: &Teams,
+        position: Vec2,
+        team: TeamId,
+        radius: f32,
+    ) -> Option<f32> {
+        units
+            .iter()
+            .filter(|(_, _, other)| teams.is_hostile(team, *other))
+            .map(|(_, other, _)| position.distance_squared(*other))
+            .filter(|distance_squared| *distance_squared <= radius * radius)
+            .min_by(f32::total_cmp)
+    }
+
+    #[test]
+    fn nearest_hostile_matches_brute_force() {
+        let mut teams = Teams::new();
+        for index in 0..4 {
+            teams.add(index.to_string(), Color::WHITE);
+        }
+        teams.set_relation(TeamId(0), TeamId(1), Relation::Ally);
+        teams.set_relation(TeamId(2), TeamId(3), Relation::Neutral);
+
+        let mut rng = RngComponent::with_seed(0x2545_f491);
+        let units: Vec<(Entity, Vec2, TeamId)> = (0..UNITS)
+            .map(|index| {
+                (
+                    Entity::from_raw(index),
+                    random_position(&mut rng, WORLD_SIZE),
+                    TeamId((index % 4) as u8),
+                )
+            })
+            .collect();
+
+        // small cells make many rings, big ones a few crowded cells
+        for cell_size in [37., SPATIAL_CELL_SIZE, 900.] {
+            let mut grid = SpatialGrid::new(cell_size);
+            for (entity, position, team) in &units {
+                grid.insert(*entity, *position, *team);
+            }
+            assert_eq!(grid.len(), units.len());
+
+            for radius in [f32::INFINITY, 0., 30., 250., 1000.] {
+                // from units, and from anywhere including out of the occupied cells
+                let positions = units
+                    .iter()
+                    .map(|(_, position, team)| (*position, *team))
+                    .chain((0..100).map(|index| {
+                        (
+                            random_position(&mut rng, WORLD_SIZE * 1.5),
+                            TeamId(index % 4),
+                        )
+                    }))
+                    .collect::<Vec<_>>();
+
+                for (position, team) in positions {
+                    let found = grid
+                        .nearest_hostile(&teams, position, team, radius)
+                        .map(|entry| {
+                            assert!(teams.is_hostile(team, entry.team));
+                            position.distance_squared(entry.position)
+                        });
+                    assert_eq!(
+                        found,
+                        brute_force(&units, &teams, position, team, radius),
+                        "cell size {cell_size}, radius {radius}, at {position}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn within_matches_brute_force() {
+        let mut rng = RngComponent::with_seed(0x1234_5678);
+        let mut grid = SpatialGrid::default();
+        let units: Vec<(Entity, Vec2)> = (0..UNITS)
+            .map(|index| {
+                let position = random_position(&mut rng, WORLD_SIZE);
+                grid.insert(Entity::from_raw(index), position, TeamId(0));
+                (Entity::from_raw(index), position)
+            })
+            .collect();
+
+        for radius in [0., 50., 300., f32::INFINITY] {
+            for (_, position) in &units {
+                let mut found: Vec<Entity> = grid
+                    .within(*position, radius)
+                    .map(|entry| entry.entity)
+                    .collect();
+                found.sort();
+                let expected: Vec<Entity> = units
+                    .iter()
+                    .filter(|(_, other)| other.distance_squared(*position) <= radius * radius)
+                    .map(|(entity, _)| *entity)
+                    .collect();
+                assert_eq!(found, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_grid_finds_nothing() {
+        let mut teams = Teams::new();
+        let team = teams.add("solo", Color::WHITE);
+        let mut grid = SpatialGrid::default();
+        assert!(grid
+            .nearest_hostile(&teams, Vec2::ZERO, team, f32::INFINITY)
+            .is_none());
+
+        grid.insert(Entity::from_raw(0), Vec2::ONE, team);
+        grid.clear();
+        assert!(grid.is_empty());
+        assert!(grid
+            .nearest_hostile(&teams, Vec2::ZERO, team, f32::INFINITY)
+            .is_none());
+    }
+}
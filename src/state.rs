@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+
+use crate::{
+    balance,
+    maps::Map,
+    physics::Collisions,
+    teams::Teams,
+    victory::{MatchEnded, Winners},
+};
+
+/// Where the game is at, gameplay systems only run `InGame`.
+///
+/// A match is spawned when leaving `MainMenu` and despawned when coming back to it.
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GameState {
+    /// waits for the balance and the map
+    #[default]
+    Loading,
+    MainMenu,
+    InGame,
+    Paused,
+    GameOver,
+}
+
+/// Despawned (with its children) when going back to the main menu.
+#[derive(Component, Default, Clone, Copy)]
+pub struct MatchEntity;
+
+/// Starts a match as soon as the main menu is reached, without waiting for the player.
+#[derive(Resource)]
+pub struct AutoStart;
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .add_systems(
+                Update,
+                (
+                    finish_loading.run_if(
+                        in_state(GameState::Loading)
+                            .and_then(balance::loaded)
+                            .and_then(resource_exists::<Map>()),
+                    ),
+                    end_match.run_if(in_state(GameState::InGame)),
+                ),
+            )
+            .add_systems(
+                OnEnter(GameState::MainMenu),
+                (teardown, start.run_if(resource_exists::<AutoStart>())),
+            );
+    }
+}
+
+fn finish_loading(mut next_state: ResMut<NextState<GameState>>) {
+    info!("[state] loaded");
+    next_state.set(GameState::MainMenu);
+}
+
+fn start(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::InGame);
+}
+
+fn teardown(mut commands: Commands, query: Query<Entity, With<MatchEntity>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands.remove_resource::<Teams>();
+    commands.remove_resource::<Winners>();
+    commands.insert_resource(Collisions::new());
+}
+
+fn end_match(
+    mut ended_events: EventReader<MatchEnded>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if ended_events.read().next().is_some() {
+        next_state.set(GameState::GameOver);
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    castles::Castle,
+    common::{GameSet, Tick},
+    health::{Damaged, Died, Health},
+    minions::Minion,
+    physics::TICK_RATE,
+    racks::Rack,
+    state::GameState,
+    teams::TeamId,
+};
+
+/// Castle health is sampled every this many ticks.
+const CASTLE_HEALTH_SAMPLE_TICKS: u64 = TICK_RATE as u64;
+
+/// What a team did during the match.
+#[derive(Default, Clone, Debug, Serialize)]
+pub struct TeamStat {
+    pub minions_spawned: u32,
+    pub minions_lost: u32,
+    pub racks_built: u32,
+    pub racks_destroyed: u32,
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub gold_earned: f32,
+    pub gold_spent: f32,
+    /// tick and health, once per second
+    pub castle_health: Vec<(u64, f32)>,
+}
+
+/// Statistics of the current match, by team, reset when a match starts.
+#[derive(Resource, Default, Clone, Debug, Serialize)]
+pub struct TeamStats {
+    // indexed by `TeamId`
+    teams: Vec<TeamStat>,
+}
+
+impl TeamStats {
+    pub fn get(&self, team: TeamId) -> Option<&TeamStat> {
+        self.teams.get(team.0 as usize)
+    }
+
+    pub fn get_mut(&mut self, team: TeamId) -> &mut TeamStat {
+        let index = team.0 as usize;
+        if index >= self.teams.len() {
+            self.teams.resize(index + 1, TeamStat::default());
+        }
+        &mut self.teams[index]
+    }
+
+    /// Every team having stats, by id.
+    pub fn iter(&self) -> impl Iterator<Item = (TeamId, &TeamStat)> {
+        self.teams
+            .iter()
+            .enumerate()
+            .map(|(index, stat)| (TeamId(index as u8), stat))
+    }
+
+    /// Health lost by `target`, dealt by `source` when it is known.
+    pub fn damage(&mut self, source: Option<TeamId>, target: Option<TeamId>, amount: f32) {
+        if let Some(source) = source {
+            self.get_mut(source).damage_dealt += amount;
+        }
+        if let Some(target) = target {
+            self.get_mut(target).damage_taken += amount;
+        }
+    }
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TeamStats>()
+            .add_systems(OnExit(GameState::MainMenu), reset)
+            .add_systems(
+                FixedUpdate,
+                (record_damage, record_deaths, sample_castles).in_set(GameSet::Cleanup),
+            );
+    }
+}
+
+fn reset(mut stats: ResMut<TeamStats>) {
+    *stats = TeamStats::default();
+}
+
+fn record_damage(
+    mut stats: ResMut<TeamStats>,
+    mut damaged_events: EventReader<Damaged>,
+    query_teams: Query<&TeamId>,
+) {
+    for damaged in damaged_events.read() {
+        // the remaining health of units dying of old age, leaving the world, etc
+        if damaged.damage.is_lethal() {
+            continue;
+        }
+        let target = query_teams.get(damaged.damage.target).ok().copied();
+        stats.damage(damaged.damage.team, target, damaged.amount);
+    }
+}
+
+// dead entities are still there in `GameSet::Cleanup`
+fn record_deaths(
+    mut stats: ResMut<TeamStats>,
+    mut died_events: EventReader<Died>,
+    query_minions: Query<(), With<Minion>>,
+    query_racks: Query<(), (With<Rack>, Without<Castle>)>,
+) {
+    for died in died_events.read() {
+        let Some(team) = died.team else {
+            continue;
+        };
+        if query_minions.contains(died.entity) {
+            stats.get_mut(team).minions_lost += 1;
+        } else if query_racks.contains(died.entity) {
+            stats.get_mut(team).racks_destroyed += 1;
+        }
+    }
+}
+
+fn sample_castles(
+    tick: Res<Tick>,
+    mut stats: ResMut<TeamStats>,
+    query: Query<(&TeamId, &Health), With<Castle>>,
+) {
+    if tick.0 % CASTLE_HEALTH_SAMPLE_TICKS != 0 {
+        return;
+    }
+
+    for (team, health) in &query {
+        stats
+            .get_mut(*team)
+            .castle_health
+            .push((tick.0, health.value));
+    }
+}
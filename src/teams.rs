@@ -1,55 +1,189 @@
-use bevy::{prelude::*, utils::HashMap};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Resource)]
+/// How a team behaves toward another one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Relation {
+    /// never attacked, never rewarded
+    Ally,
+    /// not attacked, but rewards when killed (creeps)
+    Neutral,
+    /// attacked on sight
+    #[default]
+    Hostile,
+}
+
+/// The team an entity belongs to, its metadata lives in `Teams`.
+#[derive(
+    Component, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize,
+)]
+pub struct TeamId(pub u8);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Team {
+    pub id: TeamId,
+    pub name: String,
+    pub color: Color,
+    /// lost its castle, out of the match
+    #[serde(default)]
+    pub eliminated: bool,
+}
+
+/// Teams of the current map, inserted when the map is spawned.
+///
+/// Teams are hostile to each other unless a relation says otherwise, and allied to themselves.
+#[derive(Resource, Default)]
 pub struct Teams {
-    pub map: HashMap<String, Team>,
+    // indexed by `TeamId`
+    teams: Vec<Team>,
+    // `len * len` matrix, indexed by `TeamId` pairs
+    relations: Vec<Relation>,
 }
 
 impl Teams {
     pub fn new() -> Self {
-        let mut teams = Teams {
-            map: HashMap::new(),
-        };
+        Teams {
+            teams: vec![],
+            relations: vec![],
+        }
+    }
 
-        teams.add(Team {
-            id: "a".to_string(),
-            color: Color::rgb(0.3, 0.3, 0.8),
-        });
-        teams.add(Team {
-            id: "b".to_string(),
-            color: Color::rgb(0.8, 0.3, 0.3),
-        });
-        teams.add(Team {
-            id: "c".to_string(),
-            color: Color::rgb(0.8, 0.8, 0.3),
+    /// Registers a team, hostile to every other one.
+    pub fn add(&mut self, name: impl Into<String>, color: Color) -> TeamId {
+        let id = TeamId(self.teams.len() as u8);
+        self.teams.push(Team {
+            id,
+            name: name.into(),
+            color,
+            eliminated: false,
         });
-        teams
+
+        let len = self.teams.len();
+        let mut relations = vec![Relation::default(); len * len];
+        for a in 0..len - 1 {
+            for b in 0..len - 1 {
+                relations[a * len + b] = self.relations[a * (len - 1) + b];
+            }
+        }
+        relations[id.0 as usize * len + id.0 as usize] = Relation::Ally;
+        self.relations = relations;
+
+        id
+    }
+
+    pub fn get(&self, id: TeamId) -> &Team {
+        &self.teams[id.0 as usize]
+    }
+
+    pub fn eliminate(&mut self, id: TeamId) {
+        self.teams[id.0 as usize].eliminated = true;
+    }
+
+    /// Teams still in the match, by id.
+    pub fn alive(&self) -> impl Iterator<Item = &Team> {
+        self.teams.iter().filter(|team| !team.eliminated)
+    }
+
+    pub fn find(&self, name: &str) -> Option<TeamId> {
+        self.teams
+            .iter()
+            .find(|team| team.name == name)
+            .map(|team| team.id)
+    }
+
+    /// Every team, by id.
+    pub fn iter(&self) -> impl Iterator<Item = &Team> {
+        self.teams.iter()
     }
 
-    pub fn get(&self, id: String) -> Option<&Team> {
-        self.map.get(&id)
+    pub fn len(&self) -> usize {
+        self.teams.len()
     }
 
-    pub fn get_expect(&self, id: String) -> Team {
-        self.get(id).expect("no team found").clone()
+    pub fn is_empty(&self) -> bool {
+        self.teams.is_empty()
     }
 
-    pub fn add(&mut self, team: Team) -> &mut Self {
-        self.map.insert(team.id.clone(), team);
+    pub fn set_relation(&mut self, a: TeamId, b: TeamId, relation: Relation) -> &mut Self {
+        let len = self.teams.len();
+        self.relations[a.0 as usize * len + b.0 as usize] = relation;
+        self.relations[b.0 as usize * len + a.0 as usize] = relation;
         self
     }
-}
 
-#[derive(Component, Clone)]
-pub struct Team {
-    pub id: String,
-    pub color: Color,
+    pub fn relation(&self, a: TeamId, b: TeamId) -> Relation {
+        self.relations[a.0 as usize * self.teams.len() + b.0 as usize]
+    }
+
+    pub fn is_hostile(&self, a: TeamId, b: TeamId) -> bool {
+        self.relation(a, b) == Relation::Hostile
+    }
+
+    pub fn is_ally(&self, a: TeamId, b: TeamId) -> bool {
+        self.relation(a, b) == Relation::Ally
+    }
+
+    /// Every relation between two different teams which is not the default one.
+    pub fn relations(&self) -> Vec<(TeamId, TeamId, Relation)> {
+        let mut relations = vec![];
+        for a in &self.teams {
+            for b in self.teams.iter().filter(|b| b.id > a.id) {
+                let relation = self.relation(a.id, b.id);
+                if relation != Relation::default() {
+                    relations.push((a.id, b.id, relation));
+                }
+            }
+        }
+        relations
+    }
 }
 
-pub struct TeamsPlugin;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relations_survive_new_teams() {
+        let mut teams = Teams::new();
+        let a = teams.add("a", Color::RED);
+        let b = teams.add("b", Color::GREEN);
+        let c = teams.add("c", Color::BLUE);
+        teams
+            .set_relation(a, b, Relation::Ally)
+            .set_relation(b, c, Relation::Neutral);
+
+        let d = teams.add("d", Color::WHITE);
+
+        assert_eq!(teams.len(), 4);
+        assert_eq!(teams.relation(a, b), Relation::Ally);
+        assert_eq!(teams.relation(b, a), Relation::Ally);
+        assert_eq!(teams.relation(b, c), Relation::Neutral);
+        assert_eq!(teams.relation(c, b), Relation::Neutral);
+        assert!(teams.is_hostile(a, c));
+        for other in [a, b, c] {
+            assert!(teams.is_hostile(d, other));
+            assert!(teams.is_hostile(other, d));
+        }
+        for team in [a, b, c, d] {
+            assert!(teams.is_ally(team, team));
+        }
+        assert_eq!(
+            teams.relations(),
+            vec![(a, b, Relation::Ally), (b, c, Relation::Neutral)]
+        );
+    }
+
+    #[test]
+    fn teams_are_found_by_name_and_eliminated() {
+        let mut teams = Teams::new();
+        let a = teams.add("a", Color::RED);
+        let b = teams.add("b", Color::GREEN);
+
+        assert_eq!(teams.find("b"), Some(b));
+        assert_eq!(teams.find("z"), None);
 
-impl Plugin for TeamsPlugin {
-    fn build(&self, app: &mut App) {
-        app.insert_resource(Teams::new());
+        teams.eliminate(a);
+        let alive: Vec<TeamId> = teams.alive().map(|team| team.id).collect();
+        assert_eq!(alive, vec![b]);
     }
 }
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+
+use crate::{
+    castles::Castle,
+    common::GameSet,
+    health::DamageEvent,
+    player::Player,
+    racks::Rack,
+    teams::{TeamId, Teams},
+};
+
+/// Sent once a team lost its castle, its racks are killed and its players defeated.
+#[derive(Event)]
+pub struct TeamEliminated {
+    pub team: TeamId,
+}
+
+/// Sent once no team left is hostile to another one.
+#[derive(Event, Clone)]
+pub struct MatchEnded {
+    /// the last team, or the last alliance
+    pub winners: Vec<TeamId>,
+}
+
+/// Winners of the match, inserted when it ends.
+#[derive(Resource, Clone)]
+pub struct Winners(pub Vec<TeamId>);
+
+/// A player whose team was eliminated, it can only watch.
+#[derive(Component)]
+pub struct Defeated;
+
+pub struct VictoryPlugin;
+
+impl Plugin for VictoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TeamEliminated>()
+            .add_event::<MatchEnded>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    eliminate,
+                    end_match.run_if(not(resource_exists::<Winners>())),
+                )
+                    .chain()
+                    .in_set(GameSet::Logic),
+            );
+    }
+}
+
+// a team without castle is out, whatever destroyed it
+fn eliminate(
+    mut commands: Commands,
+    mut teams: ResMut<Teams>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut eliminated_events: EventWriter<TeamEliminated>,
+    query_castles: Query<&TeamId, With<Castle>>,
+    query_racks: Query<(Entity, &TeamId), (With<Rack>, Without<Castle>)>,
+    query_players: Query<(Entity, &TeamId), With<Player>>,
+) {
+    let eliminated: Vec<TeamId> = teams
+        .alive()
+        .map(|team| team.id)
+        .filter(|id| !query_castles.iter().any(|team| team == id))
+        .collect();
+
+    for id in eliminated {
+        info!("[victory] team {} eliminated", teams.get(id).name);
+        teams.eliminate(id);
+
+        for (entity, team) in &query_racks {
+            if *team == id {
+                damage_events.send(DamageEvent::lethal(entity));
+            }
+        }
+        for (entity, team) in &query_players {
+            if *team == id {
+                commands.entity(entity).insert(Defeated);
+            }
+        }
+
+        eliminated_events.send(TeamEliminated { team: id });
+    }
+}
+
+fn end_match(mut commands: Commands, teams: Res<Teams>, mut ended_events: EventWriter<MatchEnded>) {
+    let alive: Vec<TeamId> = teams.alive().map(|team| team.id).collect();
+    let fighting = alive
+        .iter()
+        .enumerate()
+        .any(|(i, a)| alive[i + 1..].iter().any(|b| teams.is_hostile(*a, *b)));
+
+    // a single team map has nobody to fight
+    if fighting || teams.len() < 2 {
+        return;
+    }
+
+    let names: Vec<&str> = alive
+        .iter()
+        .map(|id| teams.get(*id).name.as_str())
+        .collect();
+    info!("[victory] match won by {}", names.join(", "));
+
+    commands.insert_resource(Winners(alive.clone()));
+    ended_events.send(MatchEnded { winners: alive });
+}
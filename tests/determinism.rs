@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+use game::{
+    common::Tick, config::GameConfig, headless::HeadlessPlugin, minions::Minion, GamePlugins,
+};
+
+// long enough for racks to spawn a few waves of minions
+const TICKS: u32 = 300;
+
+fn minion_positions(seed: &str) -> Vec<[f32; 3]> {
+    let mut app = App::new();
+    app.insert_resource(GameConfig {
+        seed: seed.to_string(),
+        ..default()
+    })
+    .add_plugins((
+        HeadlessPlugin {
+            ticks: None,
+            until_castle_falls: false,
+        },
+        GamePlugins.headless(),
+    ));
+    app.finish();
+    app.cleanup();
+
+    // loading takes a varying number of frames, the match starts after it
+    let mut frames = 0;
+    while app.world.resource::<Tick>().0 < u64::from(TICKS) {
+        assert!(frames < TICKS * 10, "the match never started");
+        app.update();
+        frames += 1;
+    }
+
+    let mut query = app.world.query_filtered::<&Transform, With<Minion>>();
+    let mut positions: Vec<[f32; 3]> = query
+        .iter(&app.world)
+        .map(|transform| transform.translation.to_array())
+        .collect();
+    positions.sort_by(|a, b| a.partial_cmp(b).expect("minion position is NaN"));
+    positions
+}
+
+#[test]
+fn same_seed_gives_same_minion_positions() {
+    let first = minion_positions("determinism");
+    let second = minion_positions("determinism");
+
+    assert!(!first.is_empty(), "no minion spawned");
+    assert_eq!(first, second);
+}
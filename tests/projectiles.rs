@@ -0,0 +1,88 @@
+use bevy::{
+    ecs::{event::ManualEventReader, system::RunSystemOnce},
+    prelude::*,
+};
+use game::{
+    common::Tick,
+    config::GameConfig,
+    headless::HeadlessPlugin,
+    health::Damaged,
+    minions::ProjectileBundle,
+    player::LocalPlayer,
+    teams::{TeamId, Teams},
+    GamePlugins,
+};
+
+// the projectile needs a third of a second to reach the player
+const TICKS: u32 = 60;
+
+fn start_match() -> App {
+    let mut app = App::new();
+    app.insert_resource(GameConfig::default()).add_plugins((
+        HeadlessPlugin {
+            ticks: None,
+            until_castle_falls: false,
+        },
+        GamePlugins.headless(),
+    ));
+    app.finish();
+    app.cleanup();
+
+    // loading takes a varying number of frames, the match starts after it
+    let mut frames = 0;
+    while app.world.resource::<Tick>().0 < 1 {
+        assert!(frames < TICKS * 10, "the match never started");
+        app.update();
+        frames += 1;
+    }
+    app
+}
+
+#[test]
+fn projectiles_hit_players() {
+    let mut app = start_match();
+
+    let (player, position, team) = app
+        .world
+        .query_filtered::<(Entity, &Transform, &TeamId), With<LocalPlayer>>()
+        .single(&app.world);
+    let (position, team) = (position.translation, *team);
+    let teams = app.world.resource::<Teams>();
+    let hostile = teams
+        .iter()
+        .find(|other| teams.is_hostile(team, other.id))
+        .expect("no hostile team")
+        .clone();
+    // stands for the ranged minion which shot
+    let shooter = app.world.spawn_empty().id();
+
+    app.world.run_system_once(
+        move |mut commands: Commands,
+              mut meshes: ResMut<Assets<Mesh>>,
+              mut materials: ResMut<Assets<ColorMaterial>>| {
+            commands.spawn(ProjectileBundle::new(
+                &mut meshes,
+                &mut materials,
+                Some(shooter),
+                position + Vec3::new(100., 0., 0.),
+                Vec2::new(-300., 0.),
+                1.,
+                &hostile,
+                5.,
+            ));
+        },
+    );
+
+    let mut reader = ManualEventReader::<Damaged>::default();
+    let mut hits = 0;
+    for _ in 0..TICKS {
+        app.update();
+        hits += reader
+            .read(app.world.resource::<Events<Damaged>>())
+            .filter(|damaged| {
+                damaged.damage.target == player && damaged.damage.source == Some(shooter)
+            })
+            .count();
+    }
+    assert_eq!(hits, 1, "the projectile should hit the player once");
+}